@@ -47,7 +47,10 @@ Create TYPE "TransactionErrorCode" AS ENUM (
     'WouldExceedMaxAccountCostLimit',
     'WouldExceedMaxBlockCostLimit',
     'UnsupportedVersion',
-    'InvalidWritableAccount'
+    'InvalidWritableAccount',
+    'InvalidNonce',
+    'SignatureCountMismatch',
+    'TooManyAccountLocks'
 );
 
 CREATE TYPE "TransactionError" AS (
@@ -328,6 +328,9 @@ pub enum DbTransactionErrorCode {
     WouldExceedMaxBlockCostLimit,
     UnsupportedVersion,
     InvalidWritableAccount,
+    InvalidNonce,
+    SignatureCountMismatch,
+    TooManyAccountLocks,
 }
 
 impl From<&TransactionError> for DbTransactionErrorCode {
@@ -356,6 +359,9 @@ impl From<&TransactionError> for DbTransactionErrorCode {
             TransactionError::WouldExceedMaxBlockCostLimit => Self::WouldExceedMaxBlockCostLimit,
             TransactionError::UnsupportedVersion => Self::UnsupportedVersion,
             TransactionError::InvalidWritableAccount => Self::InvalidWritableAccount,
+            TransactionError::InvalidNonce => Self::InvalidNonce,
+            TransactionError::SignatureCountMismatch { .. } => Self::SignatureCountMismatch,
+            TransactionError::TooManyAccountLocks => Self::TooManyAccountLocks,
         }
     }
 }
@@ -0,0 +1,50 @@
+#![feature(test)]
+
+extern crate test;
+use {
+    solana_sdk::{
+        hash::Hash,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+        transaction::{verify_batch, Transaction},
+    },
+    test::Bencher,
+};
+
+// Return 1024 signed transfer transactions from distinct payers
+fn create_transfer_transactions() -> Vec<Transaction> {
+    (0..1024)
+        .map(|lamports| {
+            let keypair = Keypair::new();
+            Transaction::new_signed_with_payer(
+                &[system_instruction::transfer(
+                    &keypair.pubkey(),
+                    &Pubkey::new_unique(),
+                    lamports,
+                )],
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        })
+        .collect()
+}
+
+#[bench]
+fn bench_verify_sequential(b: &mut Bencher) {
+    let txs = create_transfer_transactions();
+    b.iter(|| {
+        for tx in &txs {
+            tx.verify().unwrap();
+        }
+    });
+}
+
+#[bench]
+fn bench_verify_batch(b: &mut Bencher) {
+    let txs = create_transfer_transactions();
+    b.iter(|| {
+        assert!(verify_batch(&txs).iter().all(|result| result.is_ok()));
+    });
+}
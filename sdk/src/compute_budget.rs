@@ -38,6 +38,10 @@ pub enum ComputeBudgetInstruction {
     /// The value requested must be a multiple of 1024. This new heap frame size
     /// applies to each program executed, including all calls to CPIs.
     RequestHeapFrame(u32),
+    /// Set a compute unit price in micro-lamports to pay a higher transaction
+    /// fee for higher transaction prioritization. Not yet accepted by the
+    /// runtime's compute budget processing.
+    SetComputeUnitPrice(u64),
 }
 impl ComputeBudgetInstruction {
     /// Create a `ComputeBudgetInstruction::RequestUnits` `Instruction`
@@ -52,6 +56,14 @@ impl ComputeBudgetInstruction {
             vec![],
         )
     }
+    /// Create a `ComputeBudgetInstruction::SetComputeUnitPrice` `Instruction`
+    pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+        Instruction::new_with_borsh(
+            id(),
+            &ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports),
+            vec![],
+        )
+    }
 }
 
 #[derive(Clone, Copy, Debug, AbiExample, PartialEq)]
@@ -311,4 +323,20 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_set_compute_unit_price() {
+        let instruction = ComputeBudgetInstruction::set_compute_unit_price(1_000);
+        assert_eq!(instruction.program_id, id());
+        assert!(instruction.accounts.is_empty());
+
+        // Borsh encodes the variant index followed by the little endian price
+        let mut data = vec![2];
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+        assert_eq!(instruction.data, data);
+        assert_eq!(
+            try_from_slice_unchecked::<ComputeBudgetInstruction>(&instruction.data).unwrap(),
+            ComputeBudgetInstruction::SetComputeUnitPrice(1_000)
+        );
+    }
 }
@@ -1,7 +1,10 @@
 #![cfg(feature = "full")]
-use crate::{
-    pubkey::Pubkey,
-    signature::{Signature, Signer, SignerError},
+use {
+    crate::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signature, Signer, SignerError},
+    },
+    std::collections::{BTreeMap, HashMap},
 };
 
 /// Convenience trait for working with mixed collections of `Signer`s
@@ -47,6 +50,36 @@ macro_rules! default_keypairs_impl {
     };
 }
 
+/// Implements `Signers` for a map of keypairs by pubkey, signing with each
+/// keypair in the order of the `(pubkey, keypair)` pairs `$keypairs` returns
+macro_rules! keypair_map_impl {
+    ($keypairs:expr) => {
+        fn pubkeys(&self) -> Vec<Pubkey> {
+            $keypairs(self).map(|(pubkey, _)| *pubkey).collect()
+        }
+
+        fn try_pubkeys(&self) -> Result<Vec<Pubkey>, SignerError> {
+            Ok(self.pubkeys())
+        }
+
+        fn sign_message(&self, message: &[u8]) -> Vec<Signature> {
+            $keypairs(self)
+                .map(|(_, keypair)| keypair.sign_message(message))
+                .collect()
+        }
+
+        fn try_sign_message(&self, message: &[u8]) -> Result<Vec<Signature>, SignerError> {
+            $keypairs(self)
+                .map(|(_, keypair)| keypair.try_sign_message(message))
+                .collect()
+        }
+
+        fn is_interactive(&self) -> bool {
+            $keypairs(self).any(|(_, keypair)| keypair.is_interactive())
+        }
+    };
+}
+
 impl<T: Signer> Signers for [&T] {
     default_keypairs_impl!();
 }
@@ -111,6 +144,20 @@ impl<T: Signer> Signers for Vec<&T> {
     default_keypairs_impl!();
 }
 
+impl Signers for BTreeMap<Pubkey, Keypair> {
+    keypair_map_impl!(BTreeMap::iter);
+}
+
+fn sorted_keypairs(keypairs: &HashMap<Pubkey, Keypair>) -> std::vec::IntoIter<(&Pubkey, &Keypair)> {
+    let mut keypairs: Vec<_> = keypairs.iter().collect();
+    keypairs.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+    keypairs.into_iter()
+}
+
+impl Signers for HashMap<Pubkey, Keypair> {
+    keypair_map_impl!(sorted_keypairs);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,4 +222,45 @@ mod tests {
             vec![Signature::default(), Signature::default()],
         );
     }
+
+    #[test]
+    fn test_keypair_maps() {
+        use crate::{hash::Hash, instruction::AccountMeta, transaction::Transaction};
+
+        let keypairs: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+        let mut sorted_pubkeys: Vec<Pubkey> = keypairs.iter().map(|k| k.pubkey()).collect();
+        sorted_pubkeys.sort();
+        let instruction = crate::instruction::Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &(),
+            keypairs
+                .iter()
+                .map(|keypair| AccountMeta::new(keypair.pubkey(), true))
+                .collect(),
+        );
+        let new_tx =
+            || Transaction::new_with_payer(&[instruction.clone()], Some(&keypairs[0].pubkey()));
+
+        let btree_map: BTreeMap<Pubkey, Keypair> = keypairs
+            .iter()
+            .map(|keypair| {
+                (
+                    keypair.pubkey(),
+                    Keypair::from_bytes(&keypair.to_bytes()).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(btree_map.pubkeys(), sorted_pubkeys);
+        assert!(!btree_map.is_interactive());
+        let mut tx = new_tx();
+        tx.try_sign(&btree_map, Hash::default()).unwrap();
+        assert_eq!(tx.verify(), Ok(()));
+
+        let hash_map: HashMap<Pubkey, Keypair> = btree_map.into_iter().collect();
+        assert_eq!(hash_map.pubkeys(), sorted_pubkeys);
+        assert_eq!(hash_map.try_pubkeys(), Ok(sorted_pubkeys));
+        let mut hash_map_tx = new_tx();
+        hash_map_tx.try_sign(&hash_map, Hash::default()).unwrap();
+        assert_eq!(hash_map_tx, tx);
+    }
 }
@@ -0,0 +1,327 @@
+//! Defines a builder for assembling a transaction one instruction at a time.
+
+#![cfg(feature = "full")]
+
+use {
+    crate::{
+        hash::Hash, instruction::Instruction, message::Message, pubkey::Pubkey,
+        signature::SignerError, signers::Signers, transaction::Transaction,
+    },
+    thiserror::Error,
+};
+
+/// Reasons a `TransactionBuilder` might fail to build a transaction
+#[derive(Error, Debug, PartialEq)]
+pub enum TransactionBuilderError {
+    /// No instructions were added to the builder
+    #[error("Transaction has no instructions")]
+    NoInstructions,
+
+    /// The transaction could not be signed
+    #[error("Failed to sign transaction: {0}")]
+    SignerError(#[from] SignerError),
+
+    /// An account forced readonly is writable in the compiled transaction
+    #[error("Account {0} was forced readonly but is writable")]
+    ForcedReadonlyAccountWritable(Pubkey),
+}
+
+/// An account granted more permissions than some of the instructions referencing
+/// it requested, because another reference required them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountPromotion {
+    pub pubkey: Pubkey,
+    /// A readonly reference to the account was made writable
+    pub promoted_to_writable: bool,
+    /// A non-signer reference to the account was made a signer
+    pub promoted_to_signer: bool,
+}
+
+/// Incrementally collects the instructions, fee payer and recent blockhash of a
+/// transaction
+#[derive(Debug, Default, Clone)]
+pub struct TransactionBuilder {
+    instructions: Vec<Instruction>,
+    payer: Option<Pubkey>,
+    recent_blockhash: Hash,
+    readonly_accounts: Vec<Pubkey>,
+}
+
+impl TransactionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `instruction` to the instructions of the transaction
+    pub fn add_instruction(&mut self, instruction: Instruction) -> &mut Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// Set the fee payer of the transaction
+    pub fn set_payer(&mut self, payer: Pubkey) -> &mut Self {
+        self.payer = Some(payer);
+        self
+    }
+
+    /// Set the recent blockhash the transaction is signed with
+    pub fn set_recent_blockhash(&mut self, recent_blockhash: Hash) -> &mut Self {
+        self.recent_blockhash = recent_blockhash;
+        self
+    }
+
+    /// Require `pubkey` to be readonly in the built transaction. Building fails
+    /// if an instruction, or being the fee payer, requires it to be writable.
+    pub fn force_readonly(&mut self, pubkey: Pubkey) -> &mut Self {
+        self.readonly_accounts.push(pubkey);
+        self
+    }
+
+    /// Build an unsigned transaction from the added instructions
+    pub fn build(&self) -> Result<Transaction, TransactionBuilderError> {
+        if self.instructions.is_empty() {
+            return Err(TransactionBuilderError::NoInstructions);
+        }
+        let mut message = Message::new(&self.instructions, self.payer.as_ref());
+        message.recent_blockhash = self.recent_blockhash;
+        let tx = Transaction::new_unsigned(message);
+        if let Some(account_meta) = tx.account_metas().into_iter().find(|account_meta| {
+            account_meta.is_writable && self.readonly_accounts.contains(&account_meta.pubkey)
+        }) {
+            return Err(TransactionBuilderError::ForcedReadonlyAccountWritable(
+                account_meta.pubkey,
+            ));
+        }
+        Ok(tx)
+    }
+
+    /// Build an unsigned transaction like `build`, along with the accounts whose
+    /// permissions were promoted during compilation, in `account_keys` order
+    pub fn with_account_promotion_report(
+        &self,
+    ) -> Result<(Transaction, Vec<AccountPromotion>), TransactionBuilderError> {
+        let tx = self.build()?;
+        let account_metas = self
+            .instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts);
+        let promotions = tx
+            .account_metas()
+            .into_iter()
+            .filter_map(|compiled| {
+                let (promoted_to_writable, promoted_to_signer) = account_metas
+                    .clone()
+                    .filter(|account_meta| account_meta.pubkey == compiled.pubkey)
+                    .fold((false, false), |(writable, signer), account_meta| {
+                        (
+                            writable || (compiled.is_writable && !account_meta.is_writable),
+                            signer || (compiled.is_signer && !account_meta.is_signer),
+                        )
+                    });
+                (promoted_to_writable || promoted_to_signer).then(|| AccountPromotion {
+                    pubkey: compiled.pubkey,
+                    promoted_to_writable,
+                    promoted_to_signer,
+                })
+            })
+            .collect();
+        Ok((tx, promotions))
+    }
+
+    /// Build a transaction from the added instructions and sign it with
+    /// `keypairs`, which must include every required signer
+    pub fn build_and_sign<T: Signers>(
+        &self,
+        keypairs: &T,
+    ) -> Result<Transaction, TransactionBuilderError> {
+        let mut tx = self.build()?;
+        tx.try_sign(keypairs, self.recent_blockhash)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            hash::hash,
+            instruction::AccountMeta,
+            signature::{Keypair, Signer},
+            system_instruction,
+        },
+    };
+
+    #[test]
+    fn test_build() {
+        let payer = Keypair::new();
+        let to = Pubkey::new_unique();
+        let recent_blockhash = hash(&[1]);
+        let instructions = [
+            system_instruction::transfer(&payer.pubkey(), &to, 1),
+            system_instruction::transfer(&payer.pubkey(), &to, 2),
+        ];
+
+        let mut builder = TransactionBuilder::new();
+        builder
+            .set_payer(payer.pubkey())
+            .set_recent_blockhash(recent_blockhash);
+        assert_eq!(
+            builder.build(),
+            Err(TransactionBuilderError::NoInstructions)
+        );
+        for instruction in &instructions {
+            builder.add_instruction(instruction.clone());
+        }
+
+        let mut expected_message = Message::new(&instructions, Some(&payer.pubkey()));
+        expected_message.recent_blockhash = recent_blockhash;
+        let tx = builder.build().unwrap();
+        assert_eq!(tx, Transaction::new_unsigned(expected_message));
+        assert!(!tx.is_signed());
+
+        let tx = builder.build_and_sign(&[&payer]).unwrap();
+        assert_eq!(
+            tx,
+            Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&payer.pubkey()),
+                &[&payer],
+                recent_blockhash,
+            )
+        );
+        assert_eq!(tx.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_build_and_sign_errors() {
+        let payer = Keypair::new();
+        let mut builder = TransactionBuilder::new();
+        assert_eq!(
+            builder.build_and_sign(&[&payer]),
+            Err(TransactionBuilderError::NoInstructions)
+        );
+
+        builder
+            .add_instruction(system_instruction::transfer(
+                &payer.pubkey(),
+                &Pubkey::new_unique(),
+                1,
+            ))
+            .set_payer(payer.pubkey());
+        assert_eq!(
+            builder.build_and_sign(&[&Keypair::new()]),
+            Err(TransactionBuilderError::SignerError(
+                SignerError::KeypairPubkeyMismatch
+            ))
+        );
+    }
+
+    #[test]
+    fn test_force_readonly() {
+        let payer = Keypair::new();
+        let from = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let instruction = Instruction::new_with_bincode(
+            program_id,
+            &0,
+            vec![
+                AccountMeta::new(from, true),
+                AccountMeta::new(to, false),
+                AccountMeta::new_readonly(readonly, false),
+            ],
+        );
+
+        let mut builder = TransactionBuilder::new();
+        builder
+            .add_instruction(instruction)
+            .set_payer(payer.pubkey())
+            .force_readonly(readonly)
+            .force_readonly(program_id);
+        let tx = builder.build().unwrap();
+        let readonly_index = tx
+            .message
+            .account_keys
+            .iter()
+            .position(|key| *key == readonly);
+        assert!(!tx.message.is_writable(readonly_index.unwrap(), false));
+
+        assert_eq!(
+            builder.clone().force_readonly(to).build(),
+            Err(TransactionBuilderError::ForcedReadonlyAccountWritable(to))
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .force_readonly(from)
+                .build_and_sign(&[&payer]),
+            Err(TransactionBuilderError::ForcedReadonlyAccountWritable(from))
+        );
+        assert_eq!(
+            builder.force_readonly(payer.pubkey()).build(),
+            Err(TransactionBuilderError::ForcedReadonlyAccountWritable(
+                payer.pubkey()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_with_account_promotion_report() {
+        let payer = Keypair::new();
+        let shared = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let mut builder = TransactionBuilder::new();
+        builder
+            .add_instruction(Instruction::new_with_bincode(
+                program_id,
+                &0,
+                vec![
+                    AccountMeta::new_readonly(shared, false),
+                    AccountMeta::new_readonly(signer, false),
+                    AccountMeta::new_readonly(readonly, false),
+                ],
+            ))
+            .add_instruction(Instruction::new_with_bincode(
+                program_id,
+                &1,
+                vec![
+                    AccountMeta::new(shared, false),
+                    AccountMeta::new_readonly(signer, true),
+                    AccountMeta::new_readonly(payer.pubkey(), false),
+                ],
+            ))
+            .set_payer(payer.pubkey());
+
+        let (tx, promotions) = builder.with_account_promotion_report().unwrap();
+        assert_eq!(tx, builder.build().unwrap());
+        assert_eq!(
+            promotions,
+            vec![
+                AccountPromotion {
+                    pubkey: payer.pubkey(),
+                    promoted_to_writable: true,
+                    promoted_to_signer: true,
+                },
+                AccountPromotion {
+                    pubkey: signer,
+                    promoted_to_writable: false,
+                    promoted_to_signer: true,
+                },
+                AccountPromotion {
+                    pubkey: shared,
+                    promoted_to_writable: true,
+                    promoted_to_signer: false,
+                },
+            ]
+        );
+
+        assert_eq!(
+            TransactionBuilder::new().with_account_promotion_report(),
+            Err(TransactionBuilderError::NoInstructions)
+        );
+    }
+}
@@ -4,32 +4,48 @@
 
 use {
     crate::{
-        hash::Hash,
-        instruction::{CompiledInstruction, Instruction, InstructionError},
-        message::{Message, SanitizeMessageError},
+        borsh::try_from_slice_unchecked,
+        compute_budget::{self, ComputeBudgetInstruction},
+        hash::{hash, Hash},
+        instruction::{AccountMeta, CompiledInstruction, Instruction, InstructionError},
+        message::{Message, MessageHeader, SanitizeMessageError},
         nonce::NONCED_TX_MARKER_IX_INDEX,
+        packet::PACKET_DATA_SIZE,
         precompiles::verify_if_precompile,
         program_utils::limited_deserialize,
         pubkey::Pubkey,
+        rent::Rent,
         sanitize::{Sanitize, SanitizeError},
-        short_vec,
+        short_vec::{self, ShortU16},
         signature::{Signature, SignerError},
         signers::Signers,
     },
+    bincode::Options,
+    rayon::prelude::*,
     serde::Serialize,
     solana_program::{system_instruction::SystemInstruction, system_program},
     solana_sdk::feature_set,
+    std::collections::{BTreeSet, HashMap, HashSet},
+    std::fmt,
+    std::io::{self, Read, Write},
+    std::mem::size_of,
     std::result,
     std::sync::Arc,
     thiserror::Error,
 };
 
+mod builder;
 mod sanitized;
 mod versioned;
 
+pub use builder::*;
 pub use sanitized::*;
 pub use versioned::*;
 
+/// Maximum number of account keys a transaction may have once the
+/// `limit_tx_account_keys` feature is active
+pub const MAX_TX_ACCOUNT_KEYS: usize = 64;
+
 /// Reasons a transaction might be rejected.
 #[derive(
     Error, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, AbiExample, AbiEnumVisitor,
@@ -125,6 +141,302 @@ pub enum TransactionError {
     /// Transaction would exceed max account limit within the block
     #[error("Transaction would exceed max account limit within the block")]
     WouldExceedMaxAccountCostLimit,
+
+    /// Transaction has an invalid durable nonce advance instruction
+    #[error("Transaction has an invalid durable nonce advance instruction")]
+    InvalidNonce,
+
+    /// Transaction has a different number of signatures than its message requires
+    #[error("Transaction has {actual} signatures but its message requires {expected}")]
+    SignatureCountMismatch { expected: u8, actual: usize },
+
+    /// Transaction locks more accounts than allowed
+    #[error("Transaction locks too many accounts")]
+    TooManyAccountLocks,
+}
+
+impl TransactionError {
+    /// Return a short hint describing how a client might get a transaction
+    /// rejected with this error accepted
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            Self::AccountInUse => "retry once conflicting transactions have been processed",
+            Self::AccountLoadedTwice => "remove duplicate keys from the message account keys",
+            Self::AccountNotFound => "fund the fee payer and referenced accounts before retrying",
+            Self::ProgramAccountNotFound => "check that the invoked program is deployed",
+            Self::InsufficientFundsForFee => "add lamports to the fee payer",
+            Self::InvalidAccountForFee => "pay fees from a system-owned account",
+            Self::AlreadyProcessed => "confirm the earlier submission instead of resending",
+            Self::BlockhashNotFound => "refresh recent blockhash and re-sign",
+            Self::InstructionError(..) => "inspect the failing instruction's program logs",
+            Self::CallChainTooDeep => "reduce the depth of the program loader chain",
+            Self::MissingSignatureForFee => "sign the transaction with the fee payer",
+            Self::InvalidAccountIndex => {
+                "rebuild the message so instructions reference valid accounts"
+            }
+            Self::SignatureFailure => "re-sign the transaction with the required signers",
+            Self::InvalidProgramForExecution => "invoke an executable program account",
+            Self::SanitizeFailure => "rebuild the transaction from well-formed instructions",
+            Self::ClusterMaintenance => "wait for cluster maintenance to finish",
+            Self::AccountBorrowOutstanding => "report the program that leaked an account borrow",
+            Self::WouldExceedMaxBlockCostLimit => "resubmit for inclusion in a later block",
+            Self::UnsupportedVersion => "encode the transaction in a supported version",
+            Self::InvalidWritableAccount => "mark accounts that cannot be written as readonly",
+            Self::WouldExceedMaxAccountCostLimit => "retry later or write less contended accounts",
+            Self::InvalidNonce => "place a writable system AdvanceNonceAccount instruction first",
+            Self::SignatureCountMismatch { .. } => "provide one signature per required signer",
+            Self::TooManyAccountLocks => "split the work across several transactions",
+        }
+    }
+
+    /// Return the index of the failing instruction if this is an
+    /// `InstructionError`
+    pub fn instruction_index(&self) -> Option<u8> {
+        match self {
+            Self::InstructionError(index, _) => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Return the instruction error if this is an `InstructionError`
+    pub fn as_instruction_error(&self) -> Option<&InstructionError> {
+        match self {
+            Self::InstructionError(_, instruction_error) => Some(instruction_error),
+            _ => None,
+        }
+    }
+
+    /// Return a stable numeric code for this error.
+    ///
+    /// Codes below `INSTRUCTION_ERROR_CODE_FLAG` identify the variant and are
+    /// append-only, so existing codes never change meaning:
+    ///
+    /// | code | variant |
+    /// |------|---------|
+    /// | 0 | `AccountInUse` |
+    /// | 1 | `AccountLoadedTwice` |
+    /// | 2 | `AccountNotFound` |
+    /// | 3 | `ProgramAccountNotFound` |
+    /// | 4 | `InsufficientFundsForFee` |
+    /// | 5 | `InvalidAccountForFee` |
+    /// | 6 | `AlreadyProcessed` |
+    /// | 7 | `BlockhashNotFound` |
+    /// | 8 | reserved |
+    /// | 9 | `CallChainTooDeep` |
+    /// | 10 | `MissingSignatureForFee` |
+    /// | 11 | `InvalidAccountIndex` |
+    /// | 12 | `SignatureFailure` |
+    /// | 13 | `InvalidProgramForExecution` |
+    /// | 14 | `SanitizeFailure` |
+    /// | 15 | `ClusterMaintenance` |
+    /// | 16 | `AccountBorrowOutstanding` |
+    /// | 17 | `WouldExceedMaxBlockCostLimit` |
+    /// | 18 | `UnsupportedVersion` |
+    /// | 19 | `InvalidWritableAccount` |
+    /// | 20 | `WouldExceedMaxAccountCostLimit` |
+    /// | 21 | `InvalidNonce` |
+    /// | 22 | `SignatureCountMismatch` |
+    /// | 23 | `TooManyAccountLocks` |
+    ///
+    /// `InstructionError` codes have `INSTRUCTION_ERROR_CODE_FLAG` set, the
+    /// instruction index in the low byte and the kind of instruction error,
+    /// its position in declaration order, in the bits between. The value of a
+    /// `Custom` error, the message of a `BorshIoError` and the counts of a
+    /// `SignatureCountMismatch` are not encoded.
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            Self::AccountInUse => 0,
+            Self::AccountLoadedTwice => 1,
+            Self::AccountNotFound => 2,
+            Self::ProgramAccountNotFound => 3,
+            Self::InsufficientFundsForFee => 4,
+            Self::InvalidAccountForFee => 5,
+            Self::AlreadyProcessed => 6,
+            Self::BlockhashNotFound => 7,
+            Self::CallChainTooDeep => 9,
+            Self::MissingSignatureForFee => 10,
+            Self::InvalidAccountIndex => 11,
+            Self::SignatureFailure => 12,
+            Self::InvalidProgramForExecution => 13,
+            Self::SanitizeFailure => 14,
+            Self::ClusterMaintenance => 15,
+            Self::AccountBorrowOutstanding => 16,
+            Self::WouldExceedMaxBlockCostLimit => 17,
+            Self::UnsupportedVersion => 18,
+            Self::InvalidWritableAccount => 19,
+            Self::WouldExceedMaxAccountCostLimit => 20,
+            Self::InvalidNonce => 21,
+            Self::SignatureCountMismatch { .. } => 22,
+            Self::TooManyAccountLocks => 23,
+            Self::InstructionError(index, instruction_error) => {
+                INSTRUCTION_ERROR_CODE_FLAG
+                    | instruction_error_code(instruction_error) << 8
+                    | *index as u32
+            }
+        }
+    }
+
+    /// Return the error identified by a code produced by `to_u32`, or `None`
+    /// for unknown codes.
+    ///
+    /// Data that `to_u32` doesn't encode can't be reconstructed: code 22 comes
+    /// back as a `SignatureCountMismatch` with zero counts, a `Custom`
+    /// instruction error as `Custom(0)` and a `BorshIoError` with an empty
+    /// message.
+    pub fn from_u32(code: u32) -> Option<TransactionError> {
+        if code & INSTRUCTION_ERROR_CODE_FLAG != 0 {
+            let kind = (code & !INSTRUCTION_ERROR_CODE_FLAG) >> 8;
+            let instruction_error = instruction_error_from_code(kind)?;
+            return Some(Self::InstructionError(code as u8, instruction_error));
+        }
+        Some(match code {
+            0 => Self::AccountInUse,
+            1 => Self::AccountLoadedTwice,
+            2 => Self::AccountNotFound,
+            3 => Self::ProgramAccountNotFound,
+            4 => Self::InsufficientFundsForFee,
+            5 => Self::InvalidAccountForFee,
+            6 => Self::AlreadyProcessed,
+            7 => Self::BlockhashNotFound,
+            9 => Self::CallChainTooDeep,
+            10 => Self::MissingSignatureForFee,
+            11 => Self::InvalidAccountIndex,
+            12 => Self::SignatureFailure,
+            13 => Self::InvalidProgramForExecution,
+            14 => Self::SanitizeFailure,
+            15 => Self::ClusterMaintenance,
+            16 => Self::AccountBorrowOutstanding,
+            17 => Self::WouldExceedMaxBlockCostLimit,
+            18 => Self::UnsupportedVersion,
+            19 => Self::InvalidWritableAccount,
+            20 => Self::WouldExceedMaxAccountCostLimit,
+            21 => Self::InvalidNonce,
+            22 => Self::SignatureCountMismatch {
+                expected: 0,
+                actual: 0,
+            },
+            23 => Self::TooManyAccountLocks,
+            _ => return None,
+        })
+    }
+}
+
+/// Set in the numeric code of every `TransactionError::InstructionError`
+pub const INSTRUCTION_ERROR_CODE_FLAG: u32 = 1 << 31;
+
+/// Return the code of the kind of `instruction_error`, its position in
+/// declaration order. New kinds must only be appended.
+fn instruction_error_code(instruction_error: &InstructionError) -> u32 {
+    match instruction_error {
+        InstructionError::GenericError => 0,
+        InstructionError::InvalidArgument => 1,
+        InstructionError::InvalidInstructionData => 2,
+        InstructionError::InvalidAccountData => 3,
+        InstructionError::AccountDataTooSmall => 4,
+        InstructionError::InsufficientFunds => 5,
+        InstructionError::IncorrectProgramId => 6,
+        InstructionError::MissingRequiredSignature => 7,
+        InstructionError::AccountAlreadyInitialized => 8,
+        InstructionError::UninitializedAccount => 9,
+        InstructionError::UnbalancedInstruction => 10,
+        InstructionError::ModifiedProgramId => 11,
+        InstructionError::ExternalAccountLamportSpend => 12,
+        InstructionError::ExternalAccountDataModified => 13,
+        InstructionError::ReadonlyLamportChange => 14,
+        InstructionError::ReadonlyDataModified => 15,
+        InstructionError::DuplicateAccountIndex => 16,
+        InstructionError::ExecutableModified => 17,
+        InstructionError::RentEpochModified => 18,
+        InstructionError::NotEnoughAccountKeys => 19,
+        InstructionError::AccountDataSizeChanged => 20,
+        InstructionError::AccountNotExecutable => 21,
+        InstructionError::AccountBorrowFailed => 22,
+        InstructionError::AccountBorrowOutstanding => 23,
+        InstructionError::DuplicateAccountOutOfSync => 24,
+        InstructionError::Custom(_) => 25,
+        InstructionError::InvalidError => 26,
+        InstructionError::ExecutableDataModified => 27,
+        InstructionError::ExecutableLamportChange => 28,
+        InstructionError::ExecutableAccountNotRentExempt => 29,
+        InstructionError::UnsupportedProgramId => 30,
+        InstructionError::CallDepth => 31,
+        InstructionError::MissingAccount => 32,
+        InstructionError::ReentrancyNotAllowed => 33,
+        InstructionError::MaxSeedLengthExceeded => 34,
+        InstructionError::InvalidSeeds => 35,
+        InstructionError::InvalidRealloc => 36,
+        InstructionError::ComputationalBudgetExceeded => 37,
+        InstructionError::PrivilegeEscalation => 38,
+        InstructionError::ProgramEnvironmentSetupFailure => 39,
+        InstructionError::ProgramFailedToComplete => 40,
+        InstructionError::ProgramFailedToCompile => 41,
+        InstructionError::Immutable => 42,
+        InstructionError::IncorrectAuthority => 43,
+        InstructionError::BorshIoError(_) => 44,
+        InstructionError::AccountNotRentExempt => 45,
+        InstructionError::InvalidAccountOwner => 46,
+        InstructionError::ArithmeticOverflow => 47,
+        InstructionError::UnsupportedSysvar => 48,
+        InstructionError::IllegalOwner => 49,
+    }
+}
+
+/// Return the kind of instruction error identified by `code`, with a zero
+/// `Custom` value and an empty `BorshIoError` message
+fn instruction_error_from_code(code: u32) -> Option<InstructionError> {
+    Some(match code {
+        0 => InstructionError::GenericError,
+        1 => InstructionError::InvalidArgument,
+        2 => InstructionError::InvalidInstructionData,
+        3 => InstructionError::InvalidAccountData,
+        4 => InstructionError::AccountDataTooSmall,
+        5 => InstructionError::InsufficientFunds,
+        6 => InstructionError::IncorrectProgramId,
+        7 => InstructionError::MissingRequiredSignature,
+        8 => InstructionError::AccountAlreadyInitialized,
+        9 => InstructionError::UninitializedAccount,
+        10 => InstructionError::UnbalancedInstruction,
+        11 => InstructionError::ModifiedProgramId,
+        12 => InstructionError::ExternalAccountLamportSpend,
+        13 => InstructionError::ExternalAccountDataModified,
+        14 => InstructionError::ReadonlyLamportChange,
+        15 => InstructionError::ReadonlyDataModified,
+        16 => InstructionError::DuplicateAccountIndex,
+        17 => InstructionError::ExecutableModified,
+        18 => InstructionError::RentEpochModified,
+        19 => InstructionError::NotEnoughAccountKeys,
+        20 => InstructionError::AccountDataSizeChanged,
+        21 => InstructionError::AccountNotExecutable,
+        22 => InstructionError::AccountBorrowFailed,
+        23 => InstructionError::AccountBorrowOutstanding,
+        24 => InstructionError::DuplicateAccountOutOfSync,
+        25 => InstructionError::Custom(0),
+        26 => InstructionError::InvalidError,
+        27 => InstructionError::ExecutableDataModified,
+        28 => InstructionError::ExecutableLamportChange,
+        29 => InstructionError::ExecutableAccountNotRentExempt,
+        30 => InstructionError::UnsupportedProgramId,
+        31 => InstructionError::CallDepth,
+        32 => InstructionError::MissingAccount,
+        33 => InstructionError::ReentrancyNotAllowed,
+        34 => InstructionError::MaxSeedLengthExceeded,
+        35 => InstructionError::InvalidSeeds,
+        36 => InstructionError::InvalidRealloc,
+        37 => InstructionError::ComputationalBudgetExceeded,
+        38 => InstructionError::PrivilegeEscalation,
+        39 => InstructionError::ProgramEnvironmentSetupFailure,
+        40 => InstructionError::ProgramFailedToComplete,
+        41 => InstructionError::ProgramFailedToCompile,
+        42 => InstructionError::Immutable,
+        43 => InstructionError::IncorrectAuthority,
+        44 => InstructionError::BorshIoError(String::new()),
+        45 => InstructionError::AccountNotRentExempt,
+        46 => InstructionError::InvalidAccountOwner,
+        47 => InstructionError::ArithmeticOverflow,
+        48 => InstructionError::UnsupportedSysvar,
+        49 => InstructionError::IllegalOwner,
+        _ => return None,
+    })
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -134,6 +446,144 @@ pub enum TransactionVerificationMode {
     FullVerification,
 }
 
+impl TransactionVerificationMode {
+    /// Run the checks this mode calls for on `tx` and return its message hash:
+    /// no checks for `HashOnly`, precompile verification for
+    /// `HashAndVerifyPrecompiles`, and signature and precompile verification
+    /// for `FullVerification`
+    pub fn run(
+        &self,
+        tx: &Transaction,
+        feature_set: &Arc<feature_set::FeatureSet>,
+    ) -> Result<Option<Hash>> {
+        let message_hash = match self {
+            Self::HashOnly => tx.message_hash(),
+            Self::HashAndVerifyPrecompiles => {
+                tx.verify_precompiles(feature_set)?;
+                tx.message_hash()
+            }
+            Self::FullVerification => {
+                let message_hash = tx.verify_and_hash_message()?;
+                tx.verify_precompiles(feature_set)?;
+                message_hash
+            }
+        };
+        Ok(Some(message_hash))
+    }
+}
+
+/// The mechanism that bounds how long a transaction remains valid
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BlockhashLifetime {
+    /// The transaction advances the given durable nonce account and remains
+    /// valid until that nonce is advanced
+    DurableNonce(Pubkey),
+    /// The transaction expires once the given recent blockhash is too old
+    RecentBlockhash(Hash),
+}
+
+/// Evidence that an instruction belongs to a transaction signed by its fee payer
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct InstructionProof {
+    /// Index of the instruction within the message
+    pub index: usize,
+    /// The serialized `CompiledInstruction`
+    pub instruction: Vec<u8>,
+    /// The serialized message signed by the fee payer
+    pub message_data: Vec<u8>,
+    /// The hash of `message_data`
+    pub message_hash: Hash,
+    /// The fee payer pubkey
+    pub fee_payer: Pubkey,
+    /// The fee payer's signature over `message_data`
+    pub fee_payer_signature: Signature,
+}
+
+impl InstructionProof {
+    /// Verify that `fee_payer` is the message's fee payer and signed the message,
+    /// that the message hashes to `message_hash` and that `instruction` is the
+    /// message's instruction at `index`
+    pub fn verify(&self) -> bool {
+        if Message::hash_raw_message(&self.message_data) != self.message_hash
+            || !self
+                .fee_payer_signature
+                .verify(self.fee_payer.as_ref(), &self.message_data)
+        {
+            return false;
+        }
+        bincode::deserialize::<Message>(&self.message_data)
+            .ok()
+            .filter(|message| message.account_keys.first() == Some(&self.fee_payer))
+            .and_then(|message| {
+                let instruction = message.instructions.get(self.index)?;
+                bincode::serialize(instruction).ok()
+            })
+            .map(|instruction| instruction == self.instruction)
+            .unwrap_or(false)
+    }
+}
+
+/// Everything an offline signer needs to sign a transaction on behalf of one of
+/// its required signers
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SigningRequest {
+    /// The serialized message to sign
+    pub message_data: Vec<u8>,
+    /// The signer expected to sign
+    pub signer: Pubkey,
+    /// The position of the signer's signature in the transaction
+    pub position: usize,
+    /// The recent blockhash the message was built with
+    pub recent_blockhash: Hash,
+}
+
+/// The state of an expected signer's signature over a transaction's message
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SignerStatus {
+    /// The signer signed and the signature verifies
+    Valid,
+    /// The signer signed but the signature doesn't verify over the current message
+    Invalid,
+    /// The signer is not a required signer of the message or hasn't signed yet
+    Missing,
+}
+
+/// The status of each of a set of expected signers of a transaction
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SignerCompleteness {
+    /// Each expected signer with its status, in the order they were requested
+    pub signers: Vec<(Pubkey, SignerStatus)>,
+}
+
+impl SignerCompleteness {
+    /// Return whether every expected signer has a valid signature
+    pub fn is_complete(&self) -> bool {
+        self.signers
+            .iter()
+            .all(|(_, status)| *status == SignerStatus::Valid)
+    }
+
+    /// Return the number of expected signers with a valid signature
+    pub fn num_valid(&self) -> usize {
+        self.signers
+            .iter()
+            .filter(|(_, status)| *status == SignerStatus::Valid)
+            .count()
+    }
+}
+
+/// An instruction of a transaction with its program id and account indices
+/// resolved against the message account keys
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ResolvedInstruction<'a> {
+    /// The program invoked by the instruction
+    pub program_id: &'a Pubkey,
+    /// The accounts passed to the program, in instruction order
+    pub accounts: Vec<&'a Pubkey>,
+    /// The instruction data
+    pub data: &'a [u8],
+}
+
 pub type Result<T> = result::Result<T, TransactionError>;
 
 impl From<SanitizeError> for TransactionError {
@@ -182,6 +632,35 @@ impl Sanitize for Transaction {
     }
 }
 
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.fee_payer() {
+            Some(fee_payer) => writeln!(f, "Fee payer: {}", fee_payer)?,
+            None => writeln!(f, "Fee payer: none")?,
+        }
+        writeln!(f, "Signatures: {}", self.signatures.len())?;
+        writeln!(f, "Recent blockhash: {}", self.message.recent_blockhash)?;
+        for (i, instruction) in self.message.instructions.iter().enumerate() {
+            write!(f, "Instruction {}: program ", i)?;
+            match self
+                .message
+                .account_keys
+                .get(instruction.program_id_index as usize)
+            {
+                Some(program_id) => write!(f, "{}", program_id)?,
+                None => write!(f, "unknown")?,
+            }
+            writeln!(
+                f,
+                ", {} accounts, {} bytes of data",
+                instruction.accounts.len(),
+                instruction.data.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
 impl Transaction {
     pub fn new_unsigned(message: Message) -> Self {
         Self {
@@ -190,6 +669,31 @@ impl Transaction {
         }
     }
 
+    /// Create an unsigned transaction like `new_unsigned`, returning
+    /// `TransactionError::SanitizeFailure` if the result doesn't sanitize
+    pub fn try_new_unsigned(message: Message) -> Result<Self> {
+        let tx = Self::new_unsigned(message);
+        tx.sanitize()?;
+        Ok(tx)
+    }
+
+    /// Create a transaction from a message and its signatures, which must be
+    /// in the same order as the signers in the message account keys. Returns
+    /// `TransactionError::InvalidAccountIndex` if the number of signatures
+    /// doesn't match the number of required signatures.
+    pub fn from_message_and_signatures(
+        message: Message,
+        signatures: Vec<Signature>,
+    ) -> Result<Self> {
+        if signatures.len() != message.header.num_required_signatures as usize {
+            return Err(TransactionError::InvalidAccountIndex);
+        }
+        Ok(Self {
+            signatures,
+            message,
+        })
+    }
+
     pub fn new_with_payer(instructions: &[Instruction], payer: Option<&Pubkey>) -> Self {
         let message = Message::new(instructions, payer);
         Self::new_unsigned(message)
@@ -258,10 +762,20 @@ impl Transaction {
         Transaction::new(from_keypairs, message, recent_blockhash)
     }
 
+    #[deprecated(since = "1.9.0", note = "Please use `instruction_data_at` instead")]
     pub fn data(&self, instruction_index: usize) -> &[u8] {
         &self.message.instructions[instruction_index].data
     }
 
+    /// Return the data of the instruction at `instruction_index`, or `None` if
+    /// there is no such instruction
+    pub fn instruction_data_at(&self, instruction_index: usize) -> Option<&[u8]> {
+        self.message
+            .instructions
+            .get(instruction_index)
+            .map(|instruction| instruction.data.as_slice())
+    }
+
     fn key_index(&self, instruction_index: usize, accounts_index: usize) -> Option<usize> {
         self.message
             .instructions
@@ -292,11 +806,73 @@ impl Transaction {
         &self.message
     }
 
+    /// Return the fee payer's signature, which identifies the transaction
+    ///
+    /// Panics if the transaction has no signatures; see `try_signature`.
+    pub fn signature(&self) -> &Signature {
+        &self.signatures[0]
+    }
+
+    /// Return the fee payer's signature, or `None` if the transaction has no
+    /// signatures
+    pub fn try_signature(&self) -> Option<&Signature> {
+        self.signatures.first()
+    }
+
+    /// Return the fee payer, which is the first account key, or `None` if the
+    /// message doesn't require any signatures and so has no fee payer
+    pub fn fee_payer(&self) -> Option<&Pubkey> {
+        if self.message.header.num_required_signatures == 0 {
+            return None;
+        }
+        self.message.account_keys.first()
+    }
+
+    /// Return the size of the serialized transaction without serializing it
+    pub fn serialized_size(&self) -> Result<u64> {
+        bincode::serialized_size(self).map_err(|_| TransactionError::SanitizeFailure)
+    }
+
+    /// Return whether the serialized transaction wouldn't fit in a packet
+    pub fn is_oversized(&self) -> bool {
+        self.serialized_size()
+            .map(|size| size > PACKET_DATA_SIZE as u64)
+            .unwrap_or(true)
+    }
+
+    /// Return the number of packets of at most `mtu` bytes the serialized
+    /// transaction is split into.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mtu` is zero.
+    pub fn packet_count(&self, mtu: usize) -> Result<usize> {
+        let size = usize::try_from(self.serialized_size()?)
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        Ok(size / mtu + usize::from(size % mtu != 0))
+    }
+
     /// Return the serialized message data to sign.
     pub fn message_data(&self) -> Vec<u8> {
         self.message().serialize()
     }
 
+    /// Sanitize only the message, without checking that the signatures match the
+    /// signers it requires
+    pub fn sanitize_message(&self) -> result::Result<(), SanitizeError> {
+        self.message.sanitize()
+    }
+
+    /// Update `recent_blockhash`, reset all signatures and return the serialized
+    /// message data to be signed by external signers.
+    pub fn prepare_for_signing(&mut self, recent_blockhash: Hash) -> Vec<u8> {
+        self.message.recent_blockhash = recent_blockhash;
+        self.signatures
+            .iter_mut()
+            .for_each(|signature| *signature = Signature::default());
+        self.message_data()
+    }
+
     /// Check keys and keypair lengths, then sign this transaction.
     ///
     /// # Panics
@@ -365,7 +941,7 @@ impl Transaction {
         keypairs: &T,
         recent_blockhash: Hash,
     ) -> result::Result<(), SignerError> {
-        let positions = self.get_signing_keypair_positions(&keypairs.pubkeys())?;
+        let positions = self.get_signing_keypair_positions(&keypairs.try_pubkeys()?)?;
         if positions.iter().any(|pos| pos.is_none()) {
             return Err(SignerError::KeypairPubkeyMismatch);
         }
@@ -397,6 +973,62 @@ impl Transaction {
         Ok(())
     }
 
+    /// Sign `prehashed` rather than the serialized message, placing the
+    /// signatures in their associated positions in `signatures` without checking
+    /// that the positions are correct. Signatures are still cleared if
+    /// `recent_blockhash` changes the message.
+    ///
+    /// This is intended for signers, like hardware wallets, that sign a digest of
+    /// the message. The caller is responsible for `prehashed` being the digest of
+    /// `message_data()` that verifiers expect; it is not checked here.
+    pub fn try_partial_sign_prehashed<T: Signers>(
+        &mut self,
+        keypairs: &T,
+        positions: Vec<usize>,
+        prehashed: Hash,
+        recent_blockhash: Hash,
+    ) -> result::Result<(), SignerError> {
+        if recent_blockhash != self.message.recent_blockhash {
+            self.message.recent_blockhash = recent_blockhash;
+            self.signatures
+                .iter_mut()
+                .for_each(|signature| *signature = Signature::default());
+        }
+
+        let signatures = keypairs.try_sign_message(prehashed.as_ref())?;
+        for i in 0..positions.len() {
+            self.signatures[positions[i]] = signatures[i];
+        }
+        Ok(())
+    }
+
+    /// Sign the transaction like `try_partial_sign_unchecked`, except that a
+    /// blockhash change only clears the signatures at `positions` rather than
+    /// all of them.
+    ///
+    /// Signatures at other positions were made over the previous message, so
+    /// after a blockhash change they are stale and will fail verification
+    /// until the caller collects fresh ones from those signers.
+    pub fn try_partial_sign_preserving<T: Signers>(
+        &mut self,
+        keypairs: &T,
+        positions: Vec<usize>,
+        recent_blockhash: Hash,
+    ) -> result::Result<(), SignerError> {
+        if recent_blockhash != self.message.recent_blockhash {
+            for position in &positions {
+                self.signatures[*position] = Signature::default();
+            }
+            self.message.recent_blockhash = recent_blockhash;
+        }
+
+        let signatures = keypairs.try_sign_message(&self.message_data())?;
+        for i in 0..positions.len() {
+            self.signatures[positions[i]] = signatures[i];
+        }
+        Ok(())
+    }
+
     /// Verify the transaction
     pub fn verify(&self) -> Result<()> {
         let message_bytes = self.message_data();
@@ -411,6 +1043,59 @@ impl Transaction {
         }
     }
 
+    /// Verify the transaction, rejecting it before doing any signature
+    /// verification if it carries more than `max_signatures` signatures
+    pub fn verify_bounded(&self, max_signatures: usize) -> Result<()> {
+        if self.signatures.len() > max_signatures {
+            return Err(TransactionError::SanitizeFailure);
+        }
+        self.verify()
+    }
+
+    /// Verify the transaction, skipping signatures already known to be valid
+    /// over this exact message. Cache entries are keyed by the hash of the
+    /// serialized message so that a cached signature is never accepted for a
+    /// different message. Newly verified signatures are added to `cache`.
+    pub fn verify_cached(&self, cache: &mut HashSet<(Signature, Pubkey, Hash)>) -> Result<()> {
+        let message_bytes = self.message_data();
+        let message_hash = hash(&message_bytes);
+        for (signature, pubkey) in self.signatures.iter().zip(&self.message.account_keys) {
+            let key = (*signature, *pubkey, message_hash);
+            if cache.contains(&key) {
+                continue;
+            }
+            if !signature.verify(pubkey.as_ref(), &message_bytes) {
+                return Err(TransactionError::SignatureFailure);
+            }
+            cache.insert(key);
+        }
+        Ok(())
+    }
+
+    /// Sanitize the transaction and then verify its signatures, returning the
+    /// first failure encountered
+    pub fn verify_and_sanitize(&self) -> Result<()> {
+        self.sanitize()?;
+        self.verify()
+    }
+
+    /// Sanitize the transaction, also applying the sanitize rules gated by
+    /// features active in `feature_set`. With `limit_tx_account_keys` active, a
+    /// transaction with more than `MAX_TX_ACCOUNT_KEYS` account keys fails with
+    /// `TransactionError::TooManyAccountLocks`.
+    pub fn sanitize_with_feature_set(
+        &self,
+        feature_set: &Arc<feature_set::FeatureSet>,
+    ) -> Result<()> {
+        self.sanitize()?;
+        if feature_set.is_active(&feature_set::limit_tx_account_keys::id())
+            && self.message.account_keys.len() > MAX_TX_ACCOUNT_KEYS
+        {
+            return Err(TransactionError::TooManyAccountLocks);
+        }
+        Ok(())
+    }
+
     pub fn get_invalid_signature() -> Signature {
         Signature::default()
     }
@@ -420,6 +1105,38 @@ impl Transaction {
         self.signatures.len() == self.message.header.num_required_signatures as usize
     }
 
+    /// Verify the length of signatures matches the value in the message header,
+    /// returning `TransactionError::SignatureCountMismatch` if it doesn't
+    pub fn verify_signatures_len_strict(&self) -> Result<()> {
+        if self.verify_signatures_len() {
+            Ok(())
+        } else {
+            Err(TransactionError::SignatureCountMismatch {
+                expected: self.message.header.num_required_signatures,
+                actual: self.signatures.len(),
+            })
+        }
+    }
+
+    /// Check that every signature has a corresponding account key, the signature
+    /// bound enforced by `sanitize`, returning
+    /// `TransactionError::SignatureCountMismatch` if it doesn't
+    pub fn validate_signature_bounds(&self) -> Result<()> {
+        if self.signatures.len() > self.message.account_keys.len() {
+            Err(TransactionError::SignatureCountMismatch {
+                expected: self.message.header.num_required_signatures,
+                actual: self.signatures.len(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Hash the message without verifying any signatures
+    pub fn message_hash(&self) -> Hash {
+        Message::hash_raw_message(&self.message_data())
+    }
+
     /// Verify the transaction and hash its message
     pub fn verify_and_hash_message(&self) -> Result<Hash> {
         let message_bytes = self.message_data();
@@ -438,6 +1155,31 @@ impl Transaction {
         self._verify_with_results(&self.message_data())
     }
 
+    /// Verify the transaction's signatures and hash its message, serializing
+    /// the message only once
+    pub fn verify_with_results_and_hash(&self) -> (Vec<bool>, Hash) {
+        let message_bytes = self.message_data();
+        (
+            self._verify_with_results(&message_bytes),
+            Message::hash_raw_message(&message_bytes),
+        )
+    }
+
+    /// Verify the transaction's signatures, pairing each result with the
+    /// pubkey of the expected signer. Signatures without a corresponding
+    /// account key are paired with `Pubkey::default()` and fail verification.
+    pub fn verify_with_results_detailed(&self) -> Vec<(Pubkey, bool)> {
+        let message_bytes = self.message_data();
+        self.signatures
+            .iter()
+            .enumerate()
+            .map(|(i, signature)| match self.message.account_keys.get(i) {
+                Some(pubkey) => (*pubkey, signature.verify(pubkey.as_ref(), &message_bytes)),
+                None => (Pubkey::default(), false),
+            })
+            .collect()
+    }
+
     pub(crate) fn _verify_with_results(&self, message_bytes: &[u8]) -> Vec<bool> {
         self.signatures
             .iter()
@@ -448,10 +1190,21 @@ impl Transaction {
 
     /// Verify the precompiled programs in this transaction
     pub fn verify_precompiles(&self, feature_set: &Arc<feature_set::FeatureSet>) -> Result<()> {
-        for instruction in &self.message().instructions {
+        self.verify_precompiles_detailed(feature_set)
+            .map_err(|(_, err)| err)
+    }
+
+    /// Verify the precompiled programs in this transaction like
+    /// `verify_precompiles`, returning the index of the first failing
+    /// instruction along with the error
+    pub fn verify_precompiles_detailed(
+        &self,
+        feature_set: &Arc<feature_set::FeatureSet>,
+    ) -> result::Result<(), (usize, TransactionError)> {
+        for (i, instruction) in self.message().instructions.iter().enumerate() {
             // The Transaction may not be sanitized at this point
             if instruction.program_id_index as usize >= self.message().account_keys.len() {
-                return Err(TransactionError::AccountNotFound);
+                return Err((i, TransactionError::AccountNotFound));
             }
             let program_id = &self.message().account_keys[instruction.program_id_index as usize];
 
@@ -461,11 +1214,26 @@ impl Transaction {
                 &self.message().instructions,
                 feature_set,
             )
-            .map_err(|_| TransactionError::InvalidAccountIndex)?;
+            .map_err(|_| (i, TransactionError::InvalidAccountIndex))?;
         }
         Ok(())
     }
 
+    /// Verify the transaction's signatures and then its precompiled programs,
+    /// serializing the message only once. Equivalent to calling `verify` and
+    /// then `verify_precompiles`.
+    pub fn verify_full(&self, feature_set: &Arc<feature_set::FeatureSet>) -> Result<()> {
+        let message_bytes = self.message_data();
+        if !self
+            ._verify_with_results(&message_bytes)
+            .iter()
+            .all(|verify_result| *verify_result)
+        {
+            return Err(TransactionError::SignatureFailure);
+        }
+        self.verify_precompiles(feature_set)
+    }
+
     /// Get the positions of the pubkeys in `account_keys` associated with signing keypairs
     pub fn get_signing_keypair_positions(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<usize>>> {
         if self.message.account_keys.len() < self.message.header.num_required_signatures as usize {
@@ -506,598 +1274,5145 @@ impl Transaction {
             .iter()
             .all(|signature| *signature != Signature::default())
     }
-}
-
-pub fn uses_durable_nonce(tx: &Transaction) -> Option<&CompiledInstruction> {
-    let message = tx.message();
-    message
-        .instructions
-        .get(NONCED_TX_MARKER_IX_INDEX as usize)
-        .filter(|instruction| {
-            // Is system program
-            matches!(
-                message.account_keys.get(instruction.program_id_index as usize),
-                Some(program_id) if system_program::check_id(program_id)
-            )
-            // Is a nonce advance instruction
-            && matches!(
-                limited_deserialize(&instruction.data),
-                Ok(SystemInstruction::AdvanceNonceAccount)
-            )
-            // Nonce account is writable
-            && matches!(
-                instruction.accounts.get(0),
-                Some(index) if message.is_writable(*index as usize, true)
-            )
-        })
-}
-
-#[deprecated]
-pub fn get_nonce_pubkey_from_instruction<'a>(
-    ix: &CompiledInstruction,
-    tx: &'a Transaction,
-) -> Option<&'a Pubkey> {
-    ix.accounts.get(0).and_then(|idx| {
-        let idx = *idx as usize;
-        tx.message().account_keys.get(idx)
-    })
-}
 
-#[cfg(test)]
-mod tests {
-    #![allow(deprecated)]
+    /// Return the required signers split into `(writable, readonly)` according to
+    /// the message header.
+    pub fn signers_by_capability(&self) -> (Vec<Pubkey>, Vec<Pubkey>) {
+        let header = &self.message.header;
+        let num_signers =
+            (header.num_required_signatures as usize).min(self.message.account_keys.len());
+        let num_writable_signers =
+            num_signers.saturating_sub(header.num_readonly_signed_accounts as usize);
+        let signers = &self.message.account_keys[..num_signers];
+        (
+            signers[..num_writable_signers].to_vec(),
+            signers[num_writable_signers..].to_vec(),
+        )
+    }
 
-    use super::*;
-    use crate::{
-        hash::hash,
-        instruction::AccountMeta,
-        signature::{Keypair, Presigner, Signer},
-        system_instruction, sysvar,
-    };
-    use bincode::{deserialize, serialize, serialized_size};
-    use std::mem::size_of;
+    /// Return whether this transaction relies on a durable nonce or on its
+    /// recent blockhash to bound its lifetime
+    pub fn blockhash_lifetime(&self) -> BlockhashLifetime {
+        uses_durable_nonce(self)
+            .and_then(|nonce_ix| nonce_ix.accounts.get(0))
+            .and_then(|idx| self.message.account_keys.get(*idx as usize))
+            .map(|nonce_pubkey| BlockhashLifetime::DurableNonce(*nonce_pubkey))
+            .unwrap_or(BlockhashLifetime::RecentBlockhash(
+                self.message.recent_blockhash,
+            ))
+    }
 
-    fn get_program_id(tx: &Transaction, instruction_index: usize) -> &Pubkey {
-        let message = tx.message();
-        let instruction = &message.instructions[instruction_index];
-        instruction.program_id(&message.account_keys)
+    /// Return the number of instructions invoking each program, sorted by count
+    /// in descending order with ties broken by program id
+    pub fn instruction_counts_sorted(&self) -> Vec<(Pubkey, usize)> {
+        let mut counts = HashMap::new();
+        for instruction in &self.message.instructions {
+            if let Some(program_id) = self
+                .message
+                .account_keys
+                .get(instruction.program_id_index as usize)
+            {
+                *counts.entry(*program_id).or_insert(0) += 1;
+            }
+        }
+        let mut counts: Vec<(Pubkey, usize)> = counts.into_iter().collect();
+        counts.sort_by(|(a_id, a_count), (b_id, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_id.cmp(b_id))
+        });
+        counts
     }
 
-    #[test]
-    fn test_refs() {
-        let key = Keypair::new();
+    /// Check the account indices of every instruction against `account_keys`,
+    /// returning all out of bounds `(instruction_index, account_index)` pairs
+    /// rather than failing on the first one
+    pub fn validate_instruction_indices(&self) -> result::Result<(), Vec<(usize, u8)>> {
+        let num_account_keys = self.message.account_keys.len();
+        let invalid_indices: Vec<(usize, u8)> = self
+            .message
+            .instructions
+            .iter()
+            .enumerate()
+            .flat_map(|(instruction_index, instruction)| {
+                instruction
+                    .accounts
+                    .iter()
+                    .filter(move |account_index| **account_index as usize >= num_account_keys)
+                    .map(move |account_index| (instruction_index, *account_index))
+            })
+            .collect();
+        if invalid_indices.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid_indices)
+        }
+    }
+
+    /// Check that the program id index and account indices of every
+    /// instruction are within `account_keys`, returning
+    /// `TransactionError::InvalidAccountIndex` on the first violation
+    pub fn check_instruction_indices(&self) -> Result<()> {
+        let num_account_keys = self.message.account_keys.len();
+        for instruction in &self.message.instructions {
+            if instruction.program_id_index as usize >= num_account_keys
+                || instruction
+                    .accounts
+                    .iter()
+                    .any(|account_index| *account_index as usize >= num_account_keys)
+            {
+                return Err(TransactionError::InvalidAccountIndex);
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the index and serialized size of the largest instruction, preferring
+    /// the earliest instruction when several share the largest size
+    pub fn largest_instruction(&self) -> Option<(usize, usize)> {
+        self.message
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, instruction)| {
+                bincode::serialized_size(instruction)
+                    .ok()
+                    .map(|size| (index, size as usize))
+            })
+            .max_by(|(a_index, a_size), (b_index, b_size)| {
+                a_size.cmp(b_size).then_with(|| b_index.cmp(a_index))
+            })
+    }
+
+    /// Return the pubkey of the first signer whose signature fails verification,
+    /// stopping at the first failure
+    pub fn first_invalid_signer(&self) -> Option<Pubkey> {
+        let message_bytes = self.message_data();
+        self.signatures
+            .iter()
+            .zip(&self.message.account_keys)
+            .find(|(signature, pubkey)| !signature.verify(pubkey.as_ref(), &message_bytes))
+            .map(|(_, pubkey)| *pubkey)
+    }
+
+    /// Assemble a transaction from a message and signatures collected separately,
+    /// placing each signature in the slot of its signer. Slots without a
+    /// provided signature are left unsigned.
+    pub fn assemble(message: Message, signatures: &[(Pubkey, Signature)]) -> Result<Self> {
+        let mut tx = Self::new_unsigned(message);
+        let pubkeys: Vec<Pubkey> = signatures.iter().map(|(pubkey, _)| *pubkey).collect();
+        let positions = tx.get_signing_keypair_positions(&pubkeys)?;
+        for (position, (_, signature)) in positions.into_iter().zip(signatures) {
+            let position = position.ok_or(TransactionError::InvalidAccountIndex)?;
+            tx.signatures[position] = *signature;
+        }
+        tx.sanitize()?;
+        Ok(tx)
+    }
+
+    /// Return true if this transaction has no effect beyond fees and advancing a
+    /// durable nonce, ie. it has no instructions or its only instruction is a
+    /// nonce advance
+    pub fn is_noop(&self) -> bool {
+        match self.message.instructions.len() {
+            0 => true,
+            1 => uses_durable_nonce(self).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Return the required signers in a suggested order of collection: the fee
+    /// payer first, then the remaining writable signers and finally the readonly
+    /// signers
+    pub fn suggested_signing_order(&self) -> Vec<Pubkey> {
+        let (mut signers, readonly_signers) = self.signers_by_capability();
+        signers.extend(readonly_signers);
+        signers
+    }
+
+    /// Replace the instruction at `index`, adding any new accounts it references to
+    /// `account_keys` and dropping keys other than the fee payer that are no
+    /// longer referenced.
+    /// Instruction account indices are remapped to the new key layout and all
+    /// signatures are cleared.
+    pub fn replace_instruction(&mut self, index: usize, instruction: Instruction) -> Result<()> {
+        if index >= self.message.instructions.len() {
+            return Err(TransactionError::InvalidAccountIndex);
+        }
+        self.add_instruction_accounts(&instruction)?;
+        self.message.instructions[index] = self.message.compile_instruction(&instruction);
+        self.compact_account_keys()
+    }
+
+    /// Add the program and accounts referenced by `instruction` to `account_keys`,
+    /// granting existing keys any additional permissions it requires
+    fn add_instruction_accounts(&mut self, instruction: &Instruction) -> Result<()> {
+        let mut account_metas = self.account_metas();
+        merge_account_meta(
+            &mut account_metas,
+            AccountMeta::new_readonly(instruction.program_id, false),
+        );
+        for account_meta in &instruction.accounts {
+            merge_account_meta(&mut account_metas, account_meta.clone());
+        }
+        self.set_account_metas(account_metas)
+    }
+
+    /// Return `account_keys` with the signer and writable permissions encoded by
+    /// their position relative to the message header
+    fn account_metas(&self) -> Vec<AccountMeta> {
+        let header = &self.message.header;
+        let num_keys = self.message.account_keys.len();
+        let num_signers = header.num_required_signatures as usize;
+        let num_writable_signers =
+            num_signers.saturating_sub(header.num_readonly_signed_accounts as usize);
+        let num_writable_keys =
+            num_keys.saturating_sub(header.num_readonly_unsigned_accounts as usize);
+        self.message
+            .account_keys
+            .iter()
+            .enumerate()
+            .map(|(i, pubkey)| AccountMeta {
+                pubkey: *pubkey,
+                is_signer: i < num_signers,
+                is_writable: i < num_writable_signers
+                    || (i >= num_signers && i < num_writable_keys),
+            })
+            .collect()
+    }
+
+    /// Replace `account_keys` with `account_metas`, ordered by permissions the same
+    /// way `Message::new` orders them. The header is rebuilt, instruction account
+    /// indices are remapped and signatures are reset since the message changed.
+    fn set_account_metas(&mut self, mut account_metas: Vec<AccountMeta>) -> Result<()> {
+        if account_metas.len() > u8::MAX as usize + 1 {
+            return Err(TransactionError::InvalidAccountIndex);
+        }
+        // A stable sort keeps the fee payer first and otherwise preserves key order
+        account_metas
+            .sort_by_key(|account_meta| (!account_meta.is_signer, !account_meta.is_writable));
+        let new_positions: HashMap<Pubkey, u8> = account_metas
+            .iter()
+            .enumerate()
+            .map(|(i, account_meta)| (account_meta.pubkey, i as u8))
+            .collect();
+        let account_keys = &self.message.account_keys;
+        let remap = |index: &u8| {
+            account_keys
+                .get(*index as usize)
+                .and_then(|pubkey| new_positions.get(pubkey))
+                .copied()
+                .ok_or(TransactionError::InvalidAccountIndex)
+        };
+        let instructions = self
+            .message
+            .instructions
+            .iter()
+            .map(|instruction| {
+                Ok(CompiledInstruction {
+                    program_id_index: remap(&instruction.program_id_index)?,
+                    accounts: instruction
+                        .accounts
+                        .iter()
+                        .map(remap)
+                        .collect::<Result<_>>()?,
+                    data: instruction.data.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let header = &mut self.message.header;
+        header.num_required_signatures =
+            account_metas.iter().filter(|meta| meta.is_signer).count() as u8;
+        header.num_readonly_signed_accounts = account_metas
+            .iter()
+            .filter(|meta| meta.is_signer && !meta.is_writable)
+            .count() as u8;
+        header.num_readonly_unsigned_accounts = account_metas
+            .iter()
+            .filter(|meta| !meta.is_signer && !meta.is_writable)
+            .count() as u8;
+        self.message.account_keys = account_metas.into_iter().map(|meta| meta.pubkey).collect();
+        self.message.instructions = instructions;
+        self.signatures = vec![Signature::default(); header.num_required_signatures as usize];
+        Ok(())
+    }
+
+    /// Drop account keys, other than the fee payer, that are not referenced by
+    /// any instruction
+    fn compact_account_keys(&mut self) -> Result<()> {
+        let is_referenced = |index: usize| {
+            self.message.is_key_called_as_program(index)
+                || self.message.is_key_passed_to_program(index)
+        };
+        let account_metas = self
+            .account_metas()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| *i == 0 || is_referenced(*i))
+            .map(|(_, account_meta)| account_meta)
+            .collect::<Vec<_>>();
+        if account_metas.len() == self.message.account_keys.len() {
+            return Ok(());
+        }
+        self.set_account_metas(account_metas)
+    }
+
+    /// Estimate the lamports needed to make every account created by this
+    /// transaction's system program instructions rent exempt, under a rent model
+    /// charging `rent_per_byte_year` with an exemption threshold of `years`
+    pub fn estimated_rent_reserve(&self, rent_per_byte_year: u64, years: f64) -> u64 {
+        let rent = Rent {
+            lamports_per_byte_year: rent_per_byte_year,
+            exemption_threshold: years,
+            ..Rent::default()
+        };
+        self.message
+            .instructions
+            .iter()
+            .filter(|instruction| {
+                matches!(
+                    self.message.account_keys.get(instruction.program_id_index as usize),
+                    Some(program_id) if system_program::check_id(program_id)
+                )
+            })
+            .filter_map(|instruction| match limited_deserialize(&instruction.data) {
+                Ok(SystemInstruction::CreateAccount { space, .. })
+                | Ok(SystemInstruction::CreateAccountWithSeed { space, .. }) => Some(space),
+                _ => None,
+            })
+            .fold(0u64, |reserve, space| {
+                reserve.saturating_add(rent.minimum_balance(space as usize))
+            })
+    }
+
+    /// Return the index pairs of instructions that are identical, ie. invoke the
+    /// same program with the same accounts and data
+    pub fn duplicate_instructions(&self) -> Vec<(usize, usize)> {
+        let instructions = &self.message.instructions;
+        let mut duplicates = vec![];
+        for (i, instruction) in instructions.iter().enumerate() {
+            for (j, other) in instructions.iter().enumerate().skip(i + 1) {
+                if instruction == other {
+                    duplicates.push((i, j));
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// Return the size of the largest group of identical instructions, ie.
+    /// instructions with the same program id index, account indices and data,
+    /// or zero if there are no instructions
+    pub fn count_identical_instructions(&self) -> usize {
+        let mut counts = HashMap::new();
+        for instruction in &self.message.instructions {
+            *counts
+                .entry((
+                    instruction.program_id_index,
+                    instruction.accounts.as_slice(),
+                    instruction.data.as_slice(),
+                ))
+                .or_insert(0) += 1;
+        }
+        counts.into_values().max().unwrap_or(0)
+    }
+
+    /// Encode the serialized transaction as a base58 string
+    pub fn encode_base58(&self) -> String {
+        bs58::encode(bincode::serialize(self).unwrap()).into_string()
+    }
+
+    /// Decode and sanitize a transaction from a base58 string, rejecting
+    /// malformed input and trailing bytes
+    pub fn decode_base58(encoded: &str) -> Result<Self> {
+        let bytes = bs58::decode(encoded)
+            .into_vec()
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        Self::from_wire_bytes(&bytes)
+    }
+
+    /// Encode the serialized transaction as a base64 string
+    pub fn encode_base64(&self) -> String {
+        base64::encode(bincode::serialize(self).unwrap())
+    }
+
+    /// Decode and sanitize a transaction from a base64 string, rejecting
+    /// malformed input and trailing bytes
+    pub fn decode_base64(encoded: &str) -> Result<Self> {
+        let bytes = base64::decode(encoded).map_err(|_| TransactionError::SanitizeFailure)?;
+        Self::from_wire_bytes(&bytes)
+    }
+
+    /// Write the serialized transaction prefixed with its length as a
+    /// little-endian `u32`, so several transactions can be streamed back to back
+    pub fn write_framed<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let bytes =
+            bincode::serialize(self).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        w.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        w.write_all(&bytes)
+    }
+
+    /// Read and sanitize a transaction written by `write_framed`. Frames that
+    /// can't be read, are larger than `PACKET_DATA_SIZE` or don't hold exactly
+    /// one transaction are rejected with `TransactionError::SanitizeFailure`.
+    pub fn read_framed<R: Read>(r: &mut R) -> Result<Self> {
+        let mut len = [0u8; size_of::<u32>()];
+        r.read_exact(&mut len)
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > PACKET_DATA_SIZE {
+            return Err(TransactionError::SanitizeFailure);
+        }
+        let mut bytes = vec![0; len];
+        r.read_exact(&mut bytes)
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        Self::from_wire_bytes(&bytes)
+    }
+
+    /// Deserialize and sanitize a transaction from its wire format, rejecting
+    /// trailing bytes
+    fn from_wire_bytes(bytes: &[u8]) -> Result<Self> {
+        let tx: Self = bincode::options()
+            .with_limit(PACKET_DATA_SIZE as u64)
+            .with_fixint_encoding()
+            .reject_trailing_bytes()
+            .deserialize(bytes)
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        tx.sanitize()?;
+        Ok(tx)
+    }
+
+    /// Partition instruction indices into groups whose members don't conflict
+    /// with each other, ie. no two instructions in a group reference the same
+    /// account if either of them can write it. Groups are filled greedily in
+    /// instruction order.
+    pub fn independent_instruction_groups(&self) -> Vec<Vec<usize>> {
+        let num_account_keys = self.message.account_keys.len();
+        // (referenced, writable) account indices of each instruction
+        let instruction_accounts: Vec<(HashSet<u8>, HashSet<u8>)> = self
+            .message
+            .instructions
+            .iter()
+            .map(|instruction| {
+                let referenced: HashSet<u8> = instruction
+                    .accounts
+                    .iter()
+                    .chain(std::iter::once(&instruction.program_id_index))
+                    .copied()
+                    .filter(|index| (*index as usize) < num_account_keys)
+                    .collect();
+                let writable = referenced
+                    .iter()
+                    .copied()
+                    .filter(|index| self.message.is_writable(*index as usize, true))
+                    .collect();
+                (referenced, writable)
+            })
+            .collect();
+        let conflicts = |a: usize, b: usize| {
+            let (a_referenced, a_writable) = &instruction_accounts[a];
+            let (b_referenced, b_writable) = &instruction_accounts[b];
+            !a_writable.is_disjoint(b_referenced) || !b_writable.is_disjoint(a_referenced)
+        };
+
+        let mut groups: Vec<Vec<usize>> = vec![];
+        for index in 0..instruction_accounts.len() {
+            match groups
+                .iter_mut()
+                .find(|group| group.iter().all(|member| !conflicts(*member, index)))
+            {
+                Some(group) => group.push(index),
+                None => groups.push(vec![index]),
+            }
+        }
+        groups
+    }
+
+    /// Verify only the signatures of the given signers against the current
+    /// message, returning each pubkey paired with its verification result
+    pub fn verify_slots(&self, pubkeys: &[Pubkey]) -> Result<Vec<(Pubkey, bool)>> {
+        let positions = self.get_signing_keypair_positions(pubkeys)?;
+        let message_bytes = self.message_data();
+        pubkeys
+            .iter()
+            .zip(positions)
+            .map(|(pubkey, position)| {
+                let position = position.ok_or(TransactionError::InvalidAccountIndex)?;
+                let verified = self
+                    .signatures
+                    .get(position)
+                    .map(|signature| signature.verify(pubkey.as_ref(), &message_bytes))
+                    .unwrap_or(false);
+                Ok((*pubkey, verified))
+            })
+            .collect()
+    }
+
+    /// Produce a proof that the instruction at `index` is part of this transaction
+    /// as signed by its fee payer, or `None` if there is no such instruction or
+    /// fee payer signature
+    pub fn instruction_inclusion_proof(&self, index: usize) -> Option<InstructionProof> {
+        let instruction = bincode::serialize(self.message.instructions.get(index)?).ok()?;
+        let fee_payer = *self.message.account_keys.first()?;
+        let fee_payer_signature = *self.signatures.first()?;
+        let message_data = self.message_data();
+        Some(InstructionProof {
+            index,
+            instruction,
+            message_hash: Message::hash_raw_message(&message_data),
+            message_data,
+            fee_payer,
+            fee_payer_signature,
+        })
+    }
+
+    /// Check that the keys of the required signers are all distinct, returning
+    /// the first duplicated signer otherwise
+    pub fn validate_distinct_signers(&self) -> result::Result<(), Pubkey> {
+        let num_signers = (self.message.header.num_required_signatures as usize)
+            .min(self.message.account_keys.len());
+        let mut signers = HashSet::with_capacity(num_signers);
+        match self.message.account_keys[..num_signers]
+            .iter()
+            .find(|signer| !signers.insert(*signer))
+        {
+            Some(duplicate) => Err(*duplicate),
+            None => Ok(()),
+        }
+    }
+
+    /// Return the writable accounts, in order of first reference, that are passed
+    /// to instructions invoking `program_id`
+    pub fn writable_accounts_for_program(&self, program_id: &Pubkey) -> Vec<Pubkey> {
+        let account_keys = &self.message.account_keys;
+        let mut writable_accounts = vec![];
+        for instruction in &self.message.instructions {
+            if account_keys.get(instruction.program_id_index as usize) != Some(program_id) {
+                continue;
+            }
+            for index in &instruction.accounts {
+                let index = *index as usize;
+                if let Some(pubkey) = account_keys.get(index) {
+                    if self.message.is_writable(index, true) && !writable_accounts.contains(pubkey)
+                    {
+                        writable_accounts.push(*pubkey);
+                    }
+                }
+            }
+        }
+        writable_accounts
+    }
+
+    /// Return the accounts, in order of first reference, that are passed to
+    /// instructions invoking `program_id`. Out of bounds account indices are
+    /// skipped.
+    pub fn accounts_for_program(&self, program_id: &Pubkey) -> Vec<&Pubkey> {
+        let account_keys = &self.message.account_keys;
+        let mut accounts = vec![];
+        for instruction in &self.message.instructions {
+            if account_keys.get(instruction.program_id_index as usize) != Some(program_id) {
+                continue;
+            }
+            for pubkey in instruction
+                .accounts
+                .iter()
+                .filter_map(|index| account_keys.get(*index as usize))
+            {
+                if !accounts.contains(&pubkey) {
+                    accounts.push(pubkey);
+                }
+            }
+        }
+        accounts
+    }
+
+    /// Add a `SetComputeUnitPrice` compute budget instruction unless the
+    /// transaction already has one, returning whether an instruction was added.
+    /// The instruction is placed first, after the nonce advance of a durable
+    /// nonce transaction, and signatures are cleared when the message changes.
+    pub fn ensure_priority_fee(&mut self, micro_lamports: u64) -> Result<bool> {
+        if self.compute_budget_instructions().any(|instruction| {
+            matches!(
+                instruction,
+                ComputeBudgetInstruction::SetComputeUnitPrice(_)
+            )
+        }) {
+            return Ok(false);
+        }
+        self.insert_instruction(
+            self.compute_budget_instruction_index(),
+            ComputeBudgetInstruction::set_compute_unit_price(micro_lamports),
+        )?;
+        Ok(true)
+    }
+
+    /// Request a compute unit limit of `units`, updating existing `RequestUnits`
+    /// compute budget instructions in place or adding one where
+    /// `ensure_priority_fee` would. Signatures are cleared since the message
+    /// changes.
+    pub fn set_compute_unit_limit(&mut self, units: u32) -> Result<()> {
+        let data = ComputeBudgetInstruction::request_units(units).data;
+        let account_keys = &self.message.account_keys;
+        let mut updated = false;
+        for instruction in self.message.instructions.iter_mut() {
+            let is_compute_budget = matches!(
+                account_keys.get(instruction.program_id_index as usize),
+                Some(program_id) if compute_budget::check_id(program_id)
+            );
+            if is_compute_budget
+                && matches!(
+                    try_from_slice_unchecked(&instruction.data),
+                    Ok(ComputeBudgetInstruction::RequestUnits(_))
+                )
+            {
+                instruction.data = data.clone();
+                updated = true;
+            }
+        }
+        if updated {
+            self.signatures
+                .iter_mut()
+                .for_each(|signature| *signature = Signature::default());
+            Ok(())
+        } else {
+            self.insert_instruction(
+                self.compute_budget_instruction_index(),
+                ComputeBudgetInstruction::request_units(units),
+            )
+        }
+    }
+
+    /// Return where a new compute budget instruction goes: first, or right after
+    /// the nonce advance of a durable nonce transaction
+    fn compute_budget_instruction_index(&self) -> usize {
+        if uses_durable_nonce(self).is_some() {
+            NONCED_TX_MARKER_IX_INDEX as usize + 1
+        } else {
+            0
+        }
+    }
+
+    /// Iterate over the decoded compute budget instructions of this transaction
+    fn compute_budget_instructions(&self) -> impl Iterator<Item = ComputeBudgetInstruction> + '_ {
+        self.message
+            .instructions
+            .iter()
+            .filter(|instruction| {
+                matches!(
+                    self.message.account_keys.get(instruction.program_id_index as usize),
+                    Some(program_id) if compute_budget::check_id(program_id)
+                )
+            })
+            .filter_map(|instruction| try_from_slice_unchecked(&instruction.data).ok())
+    }
+
+    /// Insert `instruction` at `index`, adding the accounts it references to
+    /// `account_keys`. Signatures are cleared since the message changes.
+    fn insert_instruction(&mut self, index: usize, instruction: Instruction) -> Result<()> {
+        if index > self.message.instructions.len() {
+            return Err(TransactionError::InvalidAccountIndex);
+        }
+        self.add_instruction_accounts(&instruction)?;
+        let instruction = self.message.compile_instruction(&instruction);
+        self.message.instructions.insert(index, instruction);
+        Ok(())
+    }
+
+    /// Return the index in `account_keys` of each of `pubkeys`, or `None` for
+    /// pubkeys that aren't present
+    pub fn account_positions(&self, pubkeys: &[Pubkey]) -> Vec<Option<u8>> {
+        let mut positions = HashMap::with_capacity(self.message.account_keys.len());
+        for (i, pubkey) in self.message.account_keys.iter().enumerate().rev() {
+            if let Ok(i) = u8::try_from(i) {
+                positions.insert(pubkey, i);
+            }
+        }
+        pubkeys
+            .iter()
+            .map(|pubkey| positions.get(pubkey).copied())
+            .collect()
+    }
+
+    /// Check that externally supplied inner instructions, grouped by the outer
+    /// instruction that invoked them, only reference indices within `account_keys`
+    pub fn validate_inner_instruction_refs(
+        &self,
+        inner: &[Vec<CompiledInstruction>],
+    ) -> Result<()> {
+        let num_account_keys = self.message.account_keys.len();
+        let is_valid = |instruction: &CompiledInstruction| {
+            (instruction.program_id_index as usize) < num_account_keys
+                && instruction
+                    .accounts
+                    .iter()
+                    .all(|index| (*index as usize) < num_account_keys)
+        };
+        if inner.iter().flatten().all(is_valid) {
+            Ok(())
+        } else {
+            Err(TransactionError::InvalidAccountIndex)
+        }
+    }
+
+    /// Build a request for `signer` to sign this transaction offline
+    pub fn to_signing_request(&self, signer: &Pubkey) -> Result<SigningRequest> {
+        let position = self.get_signing_keypair_positions(&[*signer])?[0]
+            .ok_or(TransactionError::InvalidAccountIndex)?;
+        Ok(SigningRequest {
+            message_data: self.message_data(),
+            signer: *signer,
+            position,
+            recent_blockhash: self.message.recent_blockhash,
+        })
+    }
+
+    /// Place a signature returned for a signing request into the slot of
+    /// `signer`, rejecting signatures that don't verify against the message
+    pub fn apply_signing_response(&mut self, signer: &Pubkey, signature: Signature) -> Result<()> {
+        let position = self.get_signing_keypair_positions(&[*signer])?[0]
+            .ok_or(TransactionError::InvalidAccountIndex)?;
+        if position >= self.signatures.len() {
+            return Err(TransactionError::InvalidAccountIndex);
+        }
+        if !signature.verify(signer.as_ref(), &self.message_data()) {
+            return Err(TransactionError::SignatureFailure);
+        }
+        self.signatures[position] = signature;
+        Ok(())
+    }
+
+    /// Return false if any instruction references the fee payer through a
+    /// duplicate entry in `account_keys` rather than through index 0
+    pub fn payer_referenced_correctly(&self) -> bool {
+        let account_keys = &self.message.account_keys;
+        let payer = match account_keys.first() {
+            Some(payer) => payer,
+            None => return true,
+        };
+        self.message.instructions.iter().all(|instruction| {
+            instruction
+                .accounts
+                .iter()
+                .all(|index| *index == 0 || account_keys.get(*index as usize) != Some(payer))
+        })
+    }
+
+    /// Return the hash of each serialized instruction, in instruction order
+    pub fn instruction_hashes(&self) -> Vec<Hash> {
+        self.message
+            .instructions
+            .iter()
+            .map(|instruction| hash(&bincode::serialize(instruction).unwrap()))
+            .collect()
+    }
+
+    /// Check that the instructions invoke exactly `expected_program_sequence`,
+    /// returning an `IncorrectProgramId` instruction error for the first
+    /// instruction that doesn't match. A missing or extra instruction is
+    /// reported at the index where the sequences diverge.
+    pub fn validate_instruction_shape(&self, expected_program_sequence: &[Pubkey]) -> Result<()> {
+        let instructions = &self.message.instructions;
+        let mismatch = (0..instructions.len().max(expected_program_sequence.len())).find(|i| {
+            let program_id = instructions
+                .get(*i)
+                .and_then(|ix| self.message.account_keys.get(ix.program_id_index as usize));
+            program_id.is_none() || program_id != expected_program_sequence.get(*i)
+        });
+        match mismatch {
+            Some(index) => Err(TransactionError::InstructionError(
+                u8::try_from(index).unwrap_or(u8::MAX),
+                InstructionError::IncorrectProgramId,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Return how many slots have passed since the slot of the recent blockhash,
+    /// or `None` if the blockhash's slot is unknown or the transaction uses a
+    /// durable nonce
+    pub fn blockhash_age(
+        &self,
+        blockhash_slots: &HashMap<Hash, u64>,
+        current_slot: u64,
+    ) -> Option<u64> {
+        match self.blockhash_lifetime() {
+            BlockhashLifetime::DurableNonce(_) => None,
+            BlockhashLifetime::RecentBlockhash(blockhash) => blockhash_slots
+                .get(&blockhash)
+                .map(|slot| current_slot.saturating_sub(*slot)),
+        }
+    }
+
+    /// Return every account key and invoked program id as one sorted set
+    pub fn all_involved_pubkeys(&self) -> BTreeSet<Pubkey> {
+        let account_keys = &self.message.account_keys;
+        account_keys
+            .iter()
+            .chain(
+                self.message
+                    .instructions
+                    .iter()
+                    .filter_map(|ix| account_keys.get(ix.program_id_index as usize)),
+            )
+            .copied()
+            .collect()
+    }
+
+    /// Deserialize, sanitize and verify an untrusted wire transaction of at most
+    /// `max_size` bytes
+    pub fn ingest(bytes: &[u8], max_size: usize) -> Result<Self> {
+        if bytes.len() > max_size {
+            return Err(TransactionError::SanitizeFailure);
+        }
+        let tx = Self::from_wire_bytes(bytes)?;
+        tx.verify()?;
+        Ok(tx)
+    }
+
+    /// Return a bitmask with bit `i` set when `signatures[i]` is present. Only
+    /// the first 64 signature slots are represented.
+    pub fn signature_bitmask(&self) -> u64 {
+        self.signatures
+            .iter()
+            .take(u64::BITS as usize)
+            .enumerate()
+            .filter(|(_, signature)| **signature != Signature::default())
+            .fold(0, |bitmask, (i, _)| bitmask | (1 << i))
+    }
+
+    /// Make `new_authority` the authority of this durable nonce transaction's
+    /// nonce advance instruction, adding it to `account_keys` as a required
+    /// signer if needed and dropping the previous authority if it is no longer
+    /// referenced. Signatures are cleared since the message changes.
+    pub fn set_nonce_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        // The nonce authority is the third account of the nonce advance instruction
+        const NONCE_AUTHORITY_INDEX: usize = 2;
+        if uses_durable_nonce(self)
+            .and_then(|nonce_ix| nonce_ix.accounts.get(NONCE_AUTHORITY_INDEX))
+            .is_none()
+        {
+            return Err(TransactionError::InvalidAccountIndex);
+        }
+        let mut account_metas = self.account_metas();
+        merge_account_meta(
+            &mut account_metas,
+            AccountMeta::new_readonly(new_authority, true),
+        );
+        self.set_account_metas(account_metas)?;
+        let authority_index = self
+            .message
+            .account_keys
+            .iter()
+            .position(|pubkey| *pubkey == new_authority)
+            .ok_or(TransactionError::InvalidAccountIndex)?;
+        self.message.instructions[NONCED_TX_MARKER_IX_INDEX as usize].accounts
+            [NONCE_AUTHORITY_INDEX] = authority_index as u8;
+        self.compact_account_keys()
+    }
+
+    /// Return the number of instructions that invoke `program_id`
+    pub fn instruction_count_for(&self, program_id: &Pubkey) -> usize {
+        self.message
+            .instructions
+            .iter()
+            .filter(|instruction| {
+                self.message
+                    .account_keys
+                    .get(instruction.program_id_index as usize)
+                    == Some(program_id)
+            })
+            .count()
+    }
+
+    /// Serialize just the message, prefixed with its length as a little-endian
+    /// `u32`, for transport to offline signers
+    pub fn message_frame(&self) -> Vec<u8> {
+        let message_data = self.message_data();
+        let mut frame = Vec::with_capacity(size_of::<u32>() + message_data.len());
+        frame.extend_from_slice(&(message_data.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&message_data);
+        frame
+    }
+
+    /// Replace the message with one parsed from a frame produced by
+    /// `message_frame`, resetting all signatures
+    pub fn set_message_from_frame(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() < size_of::<u32>() {
+            return Err(TransactionError::SanitizeFailure);
+        }
+        let (len, message_data) = bytes.split_at(size_of::<u32>());
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if message_data.len() != len {
+            return Err(TransactionError::SanitizeFailure);
+        }
+        let message: Message = bincode::options()
+            .with_limit(PACKET_DATA_SIZE as u64)
+            .with_fixint_encoding()
+            .reject_trailing_bytes()
+            .deserialize(message_data)
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        self.signatures =
+            vec![Signature::default(); message.header.num_required_signatures as usize];
+        self.message = message;
+        Ok(())
+    }
+
+    /// Return whether this transaction is a valid fee bump of `original`: both
+    /// have the same fee payer and, ignoring `SetComputeUnitPrice` compute budget
+    /// instructions and the recent blockhash, the same instructions, while this
+    /// transaction requests a strictly higher compute unit price
+    pub fn is_fee_bump_of(&self, original: &Transaction) -> bool {
+        let compute_unit_price =
+            |tx: &Transaction| tx.requested_compute_unit_price().unwrap_or_default();
+        self.message.account_keys.first().is_some()
+            && self.message.account_keys.first() == original.message.account_keys.first()
+            && compute_unit_price(self) > compute_unit_price(original)
+            && matches!(
+                (
+                    self.instructions_without_compute_unit_price(),
+                    original.instructions_without_compute_unit_price(),
+                ),
+                (Some(instructions), Some(original_instructions))
+                    if instructions == original_instructions
+            )
+    }
+
+    /// Decompile the instructions of this transaction other than
+    /// `SetComputeUnitPrice` compute budget instructions, or return `None` if an
+    /// instruction references an account that doesn't exist
+    fn instructions_without_compute_unit_price(&self) -> Option<Vec<Instruction>> {
+        let account_metas = self.account_metas();
+        self.message
+            .instructions
+            .iter()
+            .filter(|instruction| {
+                let is_compute_budget_program = matches!(
+                    account_metas.get(instruction.program_id_index as usize),
+                    Some(account_meta) if compute_budget::check_id(&account_meta.pubkey)
+                );
+                !(is_compute_budget_program
+                    && matches!(
+                        try_from_slice_unchecked(&instruction.data),
+                        Ok(ComputeBudgetInstruction::SetComputeUnitPrice(_))
+                    ))
+            })
+            .map(|instruction| {
+                let program_id = account_metas
+                    .get(instruction.program_id_index as usize)?
+                    .pubkey;
+                let accounts = instruction
+                    .accounts
+                    .iter()
+                    .map(|index| account_metas.get(*index as usize).cloned())
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Instruction::new_with_bytes(
+                    program_id,
+                    &instruction.data,
+                    accounts,
+                ))
+            })
+            .collect()
+    }
+
+    /// Return whether any signature other than the default one appears in more
+    /// than one signature slot, which is almost always an error since distinct
+    /// signers can't produce identical signatures
+    pub fn has_duplicate_signatures(&self) -> bool {
+        let mut seen = HashSet::with_capacity(self.signatures.len());
+        self.signatures
+            .iter()
+            .filter(|signature| **signature != Signature::default())
+            .any(|signature| !seen.insert(signature))
+    }
+
+    /// Return a deterministic key for ordering transactions during block
+    /// packing: the requested compute unit price in micro-lamports, or zero if
+    /// none is requested, and the hash of the message as a tiebreak. Sort by
+    /// descending price and ascending hash.
+    pub fn packing_key(&self) -> (u64, Hash) {
+        (
+            self.requested_compute_unit_price().unwrap_or_default(),
+            self.message_hash(),
+        )
+    }
+
+    /// Reject transactions carrying a signature in any slot beyond the
+    /// message's `num_required_signatures`. Sanitized transactions never have
+    /// such slots, but manually constructed ones might.
+    pub fn reject_extra_signatures(&self) -> Result<()> {
+        let num_required_signatures = self.message.header.num_required_signatures as usize;
+        if self
+            .signatures
+            .iter()
+            .skip(num_required_signatures)
+            .any(|signature| *signature != Signature::default())
+        {
+            return Err(TransactionError::SanitizeFailure);
+        }
+        Ok(())
+    }
+
+    /// Return whether a compute unit limit and a compute unit price instruction,
+    /// along with the compute budget program key if it isn't already present,
+    /// could be added to this transaction without its serialized size exceeding
+    /// `PACKET_DATA_SIZE`
+    pub fn can_add_compute_budget(&self) -> bool {
+        let mut tx = self.clone();
+        let added = [
+            ComputeBudgetInstruction::request_units(0),
+            ComputeBudgetInstruction::set_compute_unit_price(0),
+        ]
+        .into_iter()
+        .try_for_each(|instruction| tx.insert_instruction(0, instruction));
+        added.is_ok() && !tx.is_oversized()
+    }
+
+    /// Classify each of `required_signers` by whether it has signed the
+    /// current message with a valid signature, signed with a signature that
+    /// doesn't verify, or not signed at all
+    pub fn signer_completeness(&self, required_signers: &[Pubkey]) -> SignerCompleteness {
+        let message_data = self.message_data();
+        let signer_keys = self.message.signer_keys();
+        let signers = required_signers
+            .iter()
+            .map(|pubkey| {
+                let status = match signer_keys
+                    .iter()
+                    .position(|signer_key| *signer_key == pubkey)
+                    .and_then(|i| self.signatures.get(i))
+                {
+                    Some(signature) if *signature != Signature::default() => {
+                        if signature.verify(pubkey.as_ref(), &message_data) {
+                            SignerStatus::Valid
+                        } else {
+                            SignerStatus::Invalid
+                        }
+                    }
+                    _ => SignerStatus::Missing,
+                };
+                (*pubkey, status)
+            })
+            .collect();
+        SignerCompleteness { signers }
+    }
+
+    /// Check that this transaction is a well-formed durable nonce transaction,
+    /// returning `TransactionError::InvalidNonce` if it isn't
+    pub fn validate_durable_nonce(&self) -> Result<()> {
+        uses_durable_nonce(self)
+            .map(|_| ())
+            .ok_or(TransactionError::InvalidNonce)
+    }
+
+    /// Return the durable nonce account advanced by this transaction, or `None`
+    /// if it isn't a durable nonce transaction
+    pub fn get_durable_nonce_pubkey(&self) -> Option<&Pubkey> {
+        uses_durable_nonce(self)
+            .and_then(|nonce_ix| nonce_ix.accounts.get(0))
+            .and_then(|index| self.message.account_keys.get(*index as usize))
+    }
+
+    /// Iterate over the program id, account pubkeys and data of each
+    /// instruction. Account indices that are out of bounds are skipped, as are
+    /// instructions whose program id index is out of bounds.
+    pub fn instructions_iter(&self) -> impl Iterator<Item = (&Pubkey, Vec<&Pubkey>, &[u8])> {
+        let account_keys = &self.message.account_keys;
+        self.message
+            .instructions
+            .iter()
+            .filter_map(move |instruction| {
+                let program_id = account_keys.get(instruction.program_id_index as usize)?;
+                let accounts = instruction
+                    .accounts
+                    .iter()
+                    .filter_map(|index| account_keys.get(*index as usize))
+                    .collect();
+                Some((program_id, accounts, instruction.data.as_slice()))
+            })
+    }
+
+    /// Return the compute unit limit requested by a `RequestUnits` compute
+    /// budget instruction, or `None` if there is no such instruction. If there
+    /// are several, the last one takes effect.
+    pub fn requested_compute_unit_limit(&self) -> Option<u32> {
+        self.compute_budget_instructions()
+            .filter_map(|instruction| match instruction {
+                ComputeBudgetInstruction::RequestUnits(units) => Some(units),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Return the compute unit price, in micro-lamports, requested by a
+    /// `SetComputeUnitPrice` compute budget instruction, or `None` if there is
+    /// no such instruction. If there are several, the last one takes effect.
+    pub fn requested_compute_unit_price(&self) -> Option<u64> {
+        self.compute_budget_instructions()
+            .filter_map(|instruction| match instruction {
+                ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports) => {
+                    Some(micro_lamports)
+                }
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Return the number of signer account keys. Like the other account count
+    /// accessors, header counts exceeding `account_keys` are clamped rather
+    /// than trusted.
+    pub fn num_signers(&self) -> usize {
+        self.message
+            .account_keys
+            .len()
+            .min(self.message.header.num_required_signatures as usize)
+    }
+
+    /// Return the number of writable signer account keys
+    pub fn num_writable_signed(&self) -> usize {
+        self.num_signers()
+            .saturating_sub(self.message.header.num_readonly_signed_accounts as usize)
+    }
+
+    /// Return the number of writable unsigned account keys
+    pub fn num_writable_unsigned(&self) -> usize {
+        self.message
+            .account_keys
+            .len()
+            .saturating_sub(self.num_signers())
+            .saturating_sub(self.message.header.num_readonly_unsigned_accounts as usize)
+    }
+
+    /// Return the number of readonly account keys, signed or not
+    pub fn num_readonly(&self) -> usize {
+        self.message.account_keys.len() - self.num_writable_signed() - self.num_writable_unsigned()
+    }
+
+    /// Set the recent blockhash, resetting all signatures if it changes since
+    /// they were made over the previous message
+    pub fn set_recent_blockhash(&mut self, blockhash: Hash) {
+        if blockhash != self.message.recent_blockhash {
+            self.message.recent_blockhash = blockhash;
+            self.signatures
+                .iter_mut()
+                .for_each(|signature| *signature = Signature::default());
+        }
+    }
+
+    /// Return an unsigned copy of this transaction using `blockhash` as its
+    /// recent blockhash, leaving this transaction untouched
+    pub fn clone_unsigned_with_blockhash(&self, blockhash: Hash) -> Transaction {
+        let mut message = self.message.clone();
+        message.recent_blockhash = blockhash;
+        Transaction::new_unsigned(message)
+    }
+
+    /// Return the account keys with later duplicates removed, preserving the
+    /// order in which keys are first seen
+    pub fn unique_account_keys(&self) -> Vec<Pubkey> {
+        let mut seen = HashSet::with_capacity(self.message.account_keys.len());
+        self.message
+            .account_keys
+            .iter()
+            .filter(|pubkey| seen.insert(*pubkey))
+            .copied()
+            .collect()
+    }
+
+    /// Estimate the fee for this transaction: `lamports_per_signature` for each
+    /// required signature plus, if a compute unit price is requested, the
+    /// prioritization fee of that price over the requested compute unit limit,
+    /// or the default limit if none is requested, rounded up to whole lamports.
+    ///
+    /// This is only an estimate; the fee charged by the runtime is authoritative.
+    pub fn calculate_fee(&self, lamports_per_signature: u64) -> u64 {
+        const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+        let signature_fee = lamports_per_signature
+            .saturating_mul(self.message.header.num_required_signatures as u64);
+        let prioritization_fee = self
+            .requested_compute_unit_price()
+            .map(|micro_lamports| {
+                let compute_unit_limit = self
+                    .requested_compute_unit_limit()
+                    .map(u64::from)
+                    .unwrap_or_else(|| compute_budget::ComputeBudget::new().max_units);
+                let micro_lamports_fee = micro_lamports as u128 * compute_unit_limit as u128;
+                let fee = (micro_lamports_fee + MICRO_LAMPORTS_PER_LAMPORT - 1)
+                    / MICRO_LAMPORTS_PER_LAMPORT;
+                u64::try_from(fee).unwrap_or(u64::MAX)
+            })
+            .unwrap_or(0);
+        signature_fee.saturating_add(prioritization_fee)
+    }
+
+    /// Return the distinct program ids invoked by this transaction in the
+    /// order they are first invoked. Out of bounds program id indices are
+    /// skipped.
+    pub fn program_ids(&self) -> Vec<&Pubkey> {
+        let mut program_ids = Vec::new();
+        for instruction in &self.message.instructions {
+            if let Some(program_id) = self
+                .message
+                .account_keys
+                .get(instruction.program_id_index as usize)
+            {
+                if !program_ids.contains(&program_id) {
+                    program_ids.push(program_id);
+                }
+            }
+        }
+        program_ids
+    }
+
+    /// Return true if this transaction has a single signature and a single
+    /// instruction, which invokes the vote program
+    pub fn is_simple_vote_transaction(&self) -> bool {
+        match self.message.instructions.as_slice() {
+            [instruction] if self.signatures.len() == 1 => matches!(
+                self.message.account_keys.get(instruction.program_id_index as usize),
+                Some(program_id) if crate::vote::program::check_id(program_id)
+            ),
+            _ => false,
+        }
+    }
+
+    /// Return the instruction at `index` with its program id and accounts
+    /// resolved, or `None` if there is no such instruction or any of its
+    /// indices are out of bounds
+    pub fn resolved_instruction(&self, index: usize) -> Option<ResolvedInstruction> {
+        let account_keys = &self.message.account_keys;
+        let instruction = self.message.instructions.get(index)?;
+        Some(ResolvedInstruction {
+            program_id: account_keys.get(instruction.program_id_index as usize)?,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|index| account_keys.get(*index as usize))
+                .collect::<Option<_>>()?,
+            data: &instruction.data,
+        })
+    }
+
+    /// Return the account keys this transaction write locks, with program ids
+    /// demoted to readonly as `Message::is_writable` does
+    pub fn writable_account_keys(&self) -> Vec<&Pubkey> {
+        self.message
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.message.is_writable(*i, true))
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    /// Return the account keys this transaction read locks, the complement of
+    /// `writable_account_keys`
+    pub fn readonly_account_keys(&self) -> Vec<&Pubkey> {
+        self.message
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.message.is_writable(*i, true))
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    /// Return the signers whose signature slot is filled, in signer order,
+    /// whether or not the signature verifies
+    pub fn signed_pubkeys(&self) -> Vec<&Pubkey> {
+        self.signatures
+            .iter()
+            .zip(&self.message.account_keys)
+            .filter(|(signature, _)| **signature != Signature::default())
+            .map(|(_, pubkey)| pubkey)
+            .collect()
+    }
+
+    /// Return the required signers that haven't signed yet, in signer order
+    pub fn unsigned_pubkeys(&self) -> Vec<&Pubkey> {
+        self.message
+            .account_keys
+            .iter()
+            .take(self.message.header.num_required_signatures as usize)
+            .enumerate()
+            .filter(|(i, _)| {
+                self.signatures
+                    .get(*i)
+                    .map_or(true, |signature| *signature == Signature::default())
+            })
+            .map(|(_, pubkey)| pubkey)
+            .collect()
+    }
+
+    /// Return the largest account index referenced by any instruction, either
+    /// as its program id or as one of its accounts, or `None` if there are no
+    /// instructions
+    pub fn max_referenced_account_index(&self) -> Option<u8> {
+        self.message
+            .instructions
+            .iter()
+            .flat_map(|instruction| {
+                std::iter::once(&instruction.program_id_index).chain(&instruction.accounts)
+            })
+            .max()
+            .copied()
+    }
+
+    /// Return the combined length of the data of every instruction
+    pub fn total_instruction_data_len(&self) -> usize {
+        self.message
+            .instructions
+            .iter()
+            .map(|instruction| instruction.data.len())
+            .sum()
+    }
+
+    /// Check that this transaction locks at most `max_locks` distinct
+    /// accounts, writable and readonly alike, returning
+    /// `TransactionError::TooManyAccountLocks` if it locks more
+    pub fn check_account_locks(&self, max_locks: usize) -> Result<()> {
+        let num_locks = self
+            .message
+            .account_keys
+            .iter()
+            .collect::<HashSet<_>>()
+            .len();
+        if num_locks > max_locks {
+            Err(TransactionError::TooManyAccountLocks)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Add `account_meta` to `account_metas`, or grant its permissions to the existing
+/// entry for the same pubkey
+fn merge_account_meta(account_metas: &mut Vec<AccountMeta>, account_meta: AccountMeta) {
+    match account_metas
+        .iter_mut()
+        .find(|existing| existing.pubkey == account_meta.pubkey)
+    {
+        Some(existing) => {
+            existing.is_signer |= account_meta.is_signer;
+            existing.is_writable |= account_meta.is_writable;
+        }
+        None => account_metas.push(account_meta),
+    }
+}
+
+pub fn uses_durable_nonce(tx: &Transaction) -> Option<&CompiledInstruction> {
+    let message = tx.message();
+    message
+        .instructions
+        .get(NONCED_TX_MARKER_IX_INDEX as usize)
+        .filter(|instruction| {
+            // Is system program
+            matches!(
+                message.account_keys.get(instruction.program_id_index as usize),
+                Some(program_id) if system_program::check_id(program_id)
+            )
+            // Is a nonce advance instruction
+            && matches!(
+                limited_deserialize(&instruction.data),
+                Ok(SystemInstruction::AdvanceNonceAccount)
+            )
+            // Nonce account is writable
+            && matches!(
+                instruction.accounts.get(0),
+                Some(index) if message.is_writable(*index as usize, true)
+            )
+        })
+}
+
+#[deprecated]
+pub fn get_nonce_pubkey_from_instruction<'a>(
+    ix: &CompiledInstruction,
+    tx: &'a Transaction,
+) -> Option<&'a Pubkey> {
+    ix.accounts.get(0).and_then(|idx| {
+        let idx = *idx as usize;
+        tx.message().account_keys.get(idx)
+    })
+}
+
+/// A transaction whose account keys are indices into a batch's shared pubkey
+/// dictionary
+#[derive(Serialize, Deserialize)]
+struct InternedTransaction {
+    #[serde(with = "short_vec")]
+    signatures: Vec<Signature>,
+    header: MessageHeader,
+    #[serde(with = "short_vec")]
+    account_key_indices: Vec<ShortU16>,
+    recent_blockhash: Hash,
+    #[serde(with = "short_vec")]
+    instructions: Vec<CompiledInstruction>,
+}
+
+/// A batch of transactions sharing one pubkey dictionary
+#[derive(Serialize, Deserialize)]
+struct InternedBatch {
+    #[serde(with = "short_vec")]
+    pubkeys: Vec<Pubkey>,
+    #[serde(with = "short_vec")]
+    transactions: Vec<InternedTransaction>,
+}
+
+/// Serialize `txs` with every distinct account key stored once in a shared
+/// dictionary, which is much smaller than serializing each transaction on its
+/// own when the transactions reference common accounts and programs. Fails with
+/// `InvalidAccountIndex` if the batch references more than 65536 distinct keys.
+pub fn serialize_batch_interned(txs: &[Transaction]) -> Result<Vec<u8>> {
+    let mut pubkeys = vec![];
+    let mut pubkey_indices = HashMap::new();
+    let transactions = txs
+        .iter()
+        .map(|tx| {
+            let account_key_indices = tx
+                .message
+                .account_keys
+                .iter()
+                .map(|pubkey| match pubkey_indices.get(pubkey) {
+                    Some(index) => Ok(ShortU16(*index)),
+                    None => {
+                        let index = u16::try_from(pubkeys.len())
+                            .map_err(|_| TransactionError::InvalidAccountIndex)?;
+                        pubkeys.push(*pubkey);
+                        pubkey_indices.insert(*pubkey, index);
+                        Ok(ShortU16(index))
+                    }
+                })
+                .collect::<Result<_>>()?;
+            Ok(InternedTransaction {
+                signatures: tx.signatures.clone(),
+                header: tx.message.header.clone(),
+                account_key_indices,
+                recent_blockhash: tx.message.recent_blockhash,
+                instructions: tx.message.instructions.clone(),
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(bincode::serialize(&InternedBatch {
+        pubkeys,
+        transactions,
+    })
+    .unwrap())
+}
+
+/// Deserialize a batch of transactions produced by `serialize_batch_interned`.
+/// Fails with `InvalidAccountIndex` if a dictionary index or an instruction's
+/// account index is out of bounds.
+pub fn deserialize_batch_interned(bytes: &[u8]) -> Result<Vec<Transaction>> {
+    let batch: InternedBatch = bincode::options()
+        .with_limit(bytes.len() as u64)
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(bytes)
+        .map_err(|_| TransactionError::SanitizeFailure)?;
+    batch
+        .transactions
+        .into_iter()
+        .map(|tx| {
+            let account_keys = tx
+                .account_key_indices
+                .iter()
+                .map(|index| batch.pubkeys.get(index.0 as usize).copied())
+                .collect::<Option<Vec<_>>>()
+                .ok_or(TransactionError::InvalidAccountIndex)?;
+            let num_account_keys = account_keys.len();
+            if tx.instructions.iter().any(|instruction| {
+                instruction.program_id_index as usize >= num_account_keys
+                    || instruction
+                        .accounts
+                        .iter()
+                        .any(|index| *index as usize >= num_account_keys)
+            }) {
+                return Err(TransactionError::InvalidAccountIndex);
+            }
+            Ok(Transaction {
+                signatures: tx.signatures,
+                message: Message {
+                    header: tx.header,
+                    account_keys,
+                    recent_blockhash: tx.recent_blockhash,
+                    instructions: tx.instructions,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Verify the signatures of each of `txs` in parallel, serializing each message
+/// once and checking its signatures in parallel too. The results are in the
+/// same order as `txs`.
+pub fn verify_batch(txs: &[Transaction]) -> Vec<Result<()>> {
+    txs.par_iter()
+        .map(|tx| {
+            let message_bytes = tx.message_data();
+            if tx
+                .signatures
+                .par_iter()
+                .zip(tx.message.account_keys.par_iter())
+                .all(|(signature, pubkey)| signature.verify(pubkey.as_ref(), &message_bytes))
+            {
+                Ok(())
+            } else {
+                Err(TransactionError::SignatureFailure)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(deprecated)]
+
+    use super::*;
+    use crate::{
+        hash::hash,
+        instruction::AccountMeta,
+        signature::{Keypair, Presigner, Signer},
+        system_instruction, sysvar,
+    };
+    use bincode::{deserialize, serialize, serialized_size};
+    use std::mem::size_of;
+
+    fn get_program_id(tx: &Transaction, instruction_index: usize) -> &Pubkey {
+        let message = tx.message();
+        let instruction = &message.instructions[instruction_index];
+        instruction.program_id(&message.account_keys)
+    }
+
+    #[test]
+    fn test_refs() {
+        let key = Keypair::new();
         let key1 = solana_sdk::pubkey::new_rand();
         let key2 = solana_sdk::pubkey::new_rand();
         let prog1 = solana_sdk::pubkey::new_rand();
         let prog2 = solana_sdk::pubkey::new_rand();
         let instructions = vec![
-            CompiledInstruction::new(3, &(), vec![0, 1]),
-            CompiledInstruction::new(4, &(), vec![0, 2]),
+            CompiledInstruction::new(3, &(), vec![0, 1]),
+            CompiledInstruction::new(4, &(), vec![0, 2]),
+        ];
+        let tx = Transaction::new_with_compiled_instructions(
+            &[&key],
+            &[key1, key2],
+            Hash::default(),
+            vec![prog1, prog2],
+            instructions,
+        );
+        assert!(tx.sanitize().is_ok());
+
+        assert_eq!(tx.key(0, 0), Some(&key.pubkey()));
+        assert_eq!(tx.signer_key(0, 0), Some(&key.pubkey()));
+
+        assert_eq!(tx.key(1, 0), Some(&key.pubkey()));
+        assert_eq!(tx.signer_key(1, 0), Some(&key.pubkey()));
+
+        assert_eq!(tx.key(0, 1), Some(&key1));
+        assert_eq!(tx.signer_key(0, 1), None);
+
+        assert_eq!(tx.key(1, 1), Some(&key2));
+        assert_eq!(tx.signer_key(1, 1), None);
+
+        assert_eq!(tx.key(2, 0), None);
+        assert_eq!(tx.signer_key(2, 0), None);
+
+        assert_eq!(tx.key(0, 2), None);
+        assert_eq!(tx.signer_key(0, 2), None);
+
+        assert_eq!(*get_program_id(&tx, 0), prog1);
+        assert_eq!(*get_program_id(&tx, 1), prog2);
+    }
+
+    #[test]
+    fn test_refs_invalid_program_id() {
+        let key = Keypair::new();
+        let instructions = vec![CompiledInstruction::new(1, &(), vec![])];
+        let tx = Transaction::new_with_compiled_instructions(
+            &[&key],
+            &[],
+            Hash::default(),
+            vec![],
+            instructions,
+        );
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+    }
+    #[test]
+    fn test_refs_invalid_account() {
+        let key = Keypair::new();
+        let instructions = vec![CompiledInstruction::new(1, &(), vec![2])];
+        let tx = Transaction::new_with_compiled_instructions(
+            &[&key],
+            &[],
+            Hash::default(),
+            vec![Pubkey::default()],
+            instructions,
+        );
+        assert_eq!(*get_program_id(&tx, 0), Pubkey::default());
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+    }
+
+    #[test]
+    fn test_sanitize_txs() {
+        let key = Keypair::new();
+        let id0 = Pubkey::default();
+        let program_id = solana_sdk::pubkey::new_rand();
+        let ix = Instruction::new_with_bincode(
+            program_id,
+            &0,
+            vec![
+                AccountMeta::new(key.pubkey(), true),
+                AccountMeta::new(id0, true),
+            ],
+        );
+        let mut tx = Transaction::new_with_payer(&[ix], Some(&key.pubkey()));
+        let o = tx.clone();
+        assert_eq!(tx.sanitize(), Ok(()));
+        assert_eq!(tx.message.account_keys.len(), 3);
+
+        tx = o.clone();
+        tx.message.header.num_required_signatures = 3;
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx = o.clone();
+        tx.message.header.num_readonly_signed_accounts = 4;
+        tx.message.header.num_readonly_unsigned_accounts = 0;
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx = o.clone();
+        tx.message.header.num_readonly_signed_accounts = 2;
+        tx.message.header.num_readonly_unsigned_accounts = 2;
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx = o.clone();
+        tx.message.header.num_readonly_signed_accounts = 0;
+        tx.message.header.num_readonly_unsigned_accounts = 4;
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx = o.clone();
+        tx.message.instructions[0].program_id_index = 3;
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx = o.clone();
+        tx.message.instructions[0].accounts[0] = 3;
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx = o.clone();
+        tx.message.instructions[0].program_id_index = 0;
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx = o.clone();
+        tx.message.header.num_readonly_signed_accounts = 2;
+        tx.message.header.num_readonly_unsigned_accounts = 3;
+        tx.message.account_keys.resize(4, Pubkey::default());
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx = o;
+        tx.message.header.num_readonly_signed_accounts = 2;
+        tx.message.header.num_required_signatures = 1;
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+    }
+
+    fn create_sample_transaction() -> Transaction {
+        let keypair = Keypair::from_bytes(&[
+            48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32, 255, 101, 36, 24, 124, 23,
+            167, 21, 132, 204, 155, 5, 185, 58, 121, 75, 156, 227, 116, 193, 215, 38, 142, 22, 8,
+            14, 229, 239, 119, 93, 5, 218, 161, 35, 3, 33, 0, 36, 100, 158, 252, 33, 161, 97, 185,
+            62, 89, 99,
+        ])
+        .unwrap();
+        let to = Pubkey::new(&[
+            1, 1, 1, 4, 5, 6, 7, 8, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 8, 7, 6, 5, 4,
+            1, 1, 1,
+        ]);
+
+        let program_id = Pubkey::new(&[
+            2, 2, 2, 4, 5, 6, 7, 8, 9, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 9, 8, 7, 6, 5, 4,
+            2, 2, 2,
+        ]);
+        let account_metas = vec![
+            AccountMeta::new(keypair.pubkey(), true),
+            AccountMeta::new(to, false),
+        ];
+        let instruction =
+            Instruction::new_with_bincode(program_id, &(1u8, 2u8, 3u8), account_metas);
+        let message = Message::new(&[instruction], Some(&keypair.pubkey()));
+        Transaction::new(&[&keypair], message, Hash::default())
+    }
+
+    #[test]
+    fn test_transaction_serialize() {
+        let tx = create_sample_transaction();
+        let ser = serialize(&tx).unwrap();
+        let deser = deserialize(&ser).unwrap();
+        assert_eq!(tx, deser);
+    }
+
+    /// Detect changes to the serialized size of payment transactions, which affects TPS.
+    #[test]
+    fn test_transaction_minimum_serialized_size() {
+        let alice_keypair = Keypair::new();
+        let alice_pubkey = alice_keypair.pubkey();
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let ix = system_instruction::transfer(&alice_pubkey, &bob_pubkey, 42);
+
+        let expected_data_size = size_of::<u32>() + size_of::<u64>();
+        assert_eq!(expected_data_size, 12);
+        assert_eq!(
+            ix.data.len(),
+            expected_data_size,
+            "unexpected system instruction size"
+        );
+
+        let expected_instruction_size = 1 + 1 + ix.accounts.len() + 1 + expected_data_size;
+        assert_eq!(expected_instruction_size, 17);
+
+        let message = Message::new(&[ix], Some(&alice_pubkey));
+        assert_eq!(
+            serialized_size(&message.instructions[0]).unwrap() as usize,
+            expected_instruction_size,
+            "unexpected Instruction::serialized_size"
+        );
+
+        let tx = Transaction::new(&[&alice_keypair], message, Hash::default());
+
+        let len_size = 1;
+        let num_required_sigs_size = 1;
+        let num_readonly_accounts_size = 2;
+        let blockhash_size = size_of::<Hash>();
+        let expected_transaction_size = len_size
+            + (tx.signatures.len() * size_of::<Signature>())
+            + num_required_sigs_size
+            + num_readonly_accounts_size
+            + len_size
+            + (tx.message.account_keys.len() * size_of::<Pubkey>())
+            + blockhash_size
+            + len_size
+            + expected_instruction_size;
+        assert_eq!(expected_transaction_size, 215);
+
+        assert_eq!(
+            serialized_size(&tx).unwrap() as usize,
+            expected_transaction_size,
+            "unexpected serialized transaction size"
+        );
+    }
+
+    /// Detect binary changes in the serialized transaction data, which could have a downstream
+    /// affect on SDKs and applications
+    #[test]
+    fn test_sdk_serialize() {
+        assert_eq!(
+            serialize(&create_sample_transaction()).unwrap(),
+            vec![
+                1, 71, 59, 9, 187, 190, 129, 150, 165, 21, 33, 158, 72, 87, 110, 144, 120, 79, 238,
+                132, 134, 105, 39, 102, 116, 209, 29, 229, 154, 36, 105, 44, 172, 118, 131, 22,
+                124, 131, 179, 142, 176, 27, 117, 160, 89, 102, 224, 204, 1, 252, 141, 2, 136, 0,
+                37, 218, 225, 129, 92, 154, 250, 59, 97, 178, 10, 1, 0, 1, 3, 156, 227, 116, 193,
+                215, 38, 142, 22, 8, 14, 229, 239, 119, 93, 5, 218, 161, 35, 3, 33, 0, 36, 100,
+                158, 252, 33, 161, 97, 185, 62, 89, 99, 1, 1, 1, 4, 5, 6, 7, 8, 9, 9, 9, 9, 9, 9,
+                9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 8, 7, 6, 5, 4, 1, 1, 1, 2, 2, 2, 4, 5, 6, 7, 8, 9, 1,
+                1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 9, 8, 7, 6, 5, 4, 2, 2, 2, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2,
+                2, 0, 1, 3, 1, 2, 3
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_transaction_missing_key() {
+        let keypair = Keypair::new();
+        let message = Message::new(&[], None);
+        Transaction::new_unsigned(message).sign(&[&keypair], Hash::default());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_partial_sign_mismatched_key() {
+        let keypair = Keypair::new();
+        let fee_payer = solana_sdk::pubkey::new_rand();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::default(),
+            &0,
+            vec![AccountMeta::new(fee_payer, true)],
+        );
+        let message = Message::new(&[ix], Some(&fee_payer));
+        Transaction::new_unsigned(message).partial_sign(&[&keypair], Hash::default());
+    }
+
+    #[test]
+    fn test_partial_sign() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let keypair2 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::default(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+                AccountMeta::new(keypair2.pubkey(), true),
+            ],
+        );
+        let message = Message::new(&[ix], Some(&keypair0.pubkey()));
+        let mut tx = Transaction::new_unsigned(message);
+
+        tx.partial_sign(&[&keypair0, &keypair2], Hash::default());
+        assert!(!tx.is_signed());
+        tx.partial_sign(&[&keypair1], Hash::default());
+        assert!(tx.is_signed());
+
+        let hash = hash(&[1]);
+        tx.partial_sign(&[&keypair1], hash);
+        assert!(!tx.is_signed());
+        tx.partial_sign(&[&keypair0, &keypair2], hash);
+        assert!(tx.is_signed());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_transaction_missing_keypair() {
+        let program_id = Pubkey::default();
+        let keypair0 = Keypair::new();
+        let id0 = keypair0.pubkey();
+        let ix = Instruction::new_with_bincode(program_id, &0, vec![AccountMeta::new(id0, true)]);
+        let message = Message::new(&[ix], Some(&id0));
+        Transaction::new_unsigned(message).sign(&Vec::<&Keypair>::new(), Hash::default());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_transaction_wrong_key() {
+        let program_id = Pubkey::default();
+        let keypair0 = Keypair::new();
+        let wrong_id = Pubkey::default();
+        let ix =
+            Instruction::new_with_bincode(program_id, &0, vec![AccountMeta::new(wrong_id, true)]);
+        let message = Message::new(&[ix], Some(&wrong_id));
+        Transaction::new_unsigned(message).sign(&[&keypair0], Hash::default());
+    }
+
+    #[test]
+    fn test_transaction_correct_key() {
+        let program_id = Pubkey::default();
+        let keypair0 = Keypair::new();
+        let id0 = keypair0.pubkey();
+        let ix = Instruction::new_with_bincode(program_id, &0, vec![AccountMeta::new(id0, true)]);
+        let message = Message::new(&[ix], Some(&id0));
+        let mut tx = Transaction::new_unsigned(message);
+        tx.sign(&[&keypair0], Hash::default());
+        assert_eq!(
+            tx.message.instructions[0],
+            CompiledInstruction::new(1, &0, vec![0])
+        );
+        assert!(tx.is_signed());
+    }
+
+    #[test]
+    fn test_transaction_instruction_with_duplicate_keys() {
+        let program_id = Pubkey::default();
+        let keypair0 = Keypair::new();
+        let id0 = keypair0.pubkey();
+        let id1 = solana_sdk::pubkey::new_rand();
+        let ix = Instruction::new_with_bincode(
+            program_id,
+            &0,
+            vec![
+                AccountMeta::new(id0, true),
+                AccountMeta::new(id1, false),
+                AccountMeta::new(id0, false),
+                AccountMeta::new(id1, false),
+            ],
+        );
+        let message = Message::new(&[ix], Some(&id0));
+        let mut tx = Transaction::new_unsigned(message);
+        tx.sign(&[&keypair0], Hash::default());
+        assert_eq!(
+            tx.message.instructions[0],
+            CompiledInstruction::new(2, &0, vec![0, 1, 0, 1])
+        );
+        assert!(tx.is_signed());
+    }
+
+    #[test]
+    fn test_try_sign_dyn_keypairs() {
+        let program_id = Pubkey::default();
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let presigner_keypair = Keypair::new();
+        let presigner_pubkey = presigner_keypair.pubkey();
+
+        let ix = Instruction::new_with_bincode(
+            program_id,
+            &0,
+            vec![
+                AccountMeta::new(pubkey, true),
+                AccountMeta::new(presigner_pubkey, true),
+            ],
+        );
+        let message = Message::new(&[ix], Some(&pubkey));
+        let mut tx = Transaction::new_unsigned(message);
+
+        let presigner_sig = presigner_keypair.sign_message(&tx.message_data());
+        let presigner = Presigner::new(&presigner_pubkey, &presigner_sig);
+
+        let signers: Vec<&dyn Signer> = vec![&keypair, &presigner];
+
+        let res = tx.try_sign(&signers, Hash::default());
+        assert_eq!(res, Ok(()));
+        assert_eq!(tx.signatures[0], keypair.sign_message(&tx.message_data()));
+        assert_eq!(tx.signatures[1], presigner_sig);
+
+        // Wrong key should error, not panic
+        let another_pubkey = solana_sdk::pubkey::new_rand();
+        let ix = Instruction::new_with_bincode(
+            program_id,
+            &0,
+            vec![
+                AccountMeta::new(another_pubkey, true),
+                AccountMeta::new(presigner_pubkey, true),
+            ],
+        );
+        let message = Message::new(&[ix], Some(&another_pubkey));
+        let mut tx = Transaction::new_unsigned(message);
+
+        let res = tx.try_sign(&signers, Hash::default());
+        assert!(res.is_err());
+        assert_eq!(
+            tx.signatures,
+            vec![Signature::default(), Signature::default()]
+        );
+    }
+
+    fn nonced_transfer_tx() -> (Pubkey, Pubkey, Transaction) {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let nonce_keypair = Keypair::new();
+        let nonce_pubkey = nonce_keypair.pubkey();
+        let instructions = [
+            system_instruction::advance_nonce_account(&nonce_pubkey, &nonce_pubkey),
+            system_instruction::transfer(&from_pubkey, &nonce_pubkey, 42),
+        ];
+        let message = Message::new(&instructions, Some(&nonce_pubkey));
+        let tx = Transaction::new(&[&from_keypair, &nonce_keypair], message, Hash::default());
+        (from_pubkey, nonce_pubkey, tx)
+    }
+
+    #[test]
+    fn tx_uses_nonce_ok() {
+        let (_, _, tx) = nonced_transfer_tx();
+        assert!(uses_durable_nonce(&tx).is_some());
+    }
+
+    #[test]
+    fn tx_uses_nonce_empty_ix_fail() {
+        assert!(uses_durable_nonce(&Transaction::default()).is_none());
+    }
+
+    #[test]
+    fn tx_uses_nonce_bad_prog_id_idx_fail() {
+        let (_, _, mut tx) = nonced_transfer_tx();
+        tx.message.instructions.get_mut(0).unwrap().program_id_index = 255u8;
+        assert!(uses_durable_nonce(&tx).is_none());
+    }
+
+    #[test]
+    fn tx_uses_nonce_first_prog_id_not_nonce_fail() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let nonce_keypair = Keypair::new();
+        let nonce_pubkey = nonce_keypair.pubkey();
+        let instructions = [
+            system_instruction::transfer(&from_pubkey, &nonce_pubkey, 42),
+            system_instruction::advance_nonce_account(&nonce_pubkey, &nonce_pubkey),
+        ];
+        let message = Message::new(&instructions, Some(&from_pubkey));
+        let tx = Transaction::new(&[&from_keypair, &nonce_keypair], message, Hash::default());
+        assert!(uses_durable_nonce(&tx).is_none());
+    }
+
+    #[test]
+    fn tx_uses_ro_nonce_account() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let nonce_keypair = Keypair::new();
+        let nonce_pubkey = nonce_keypair.pubkey();
+        let account_metas = vec![
+            AccountMeta::new_readonly(nonce_pubkey, false),
+            #[allow(deprecated)]
+            AccountMeta::new_readonly(sysvar::recent_blockhashes::id(), false),
+            AccountMeta::new_readonly(nonce_pubkey, true),
+        ];
+        let nonce_instruction = Instruction::new_with_bincode(
+            system_program::id(),
+            &system_instruction::SystemInstruction::AdvanceNonceAccount,
+            account_metas,
+        );
+        let tx = Transaction::new_signed_with_payer(
+            &[nonce_instruction],
+            Some(&from_pubkey),
+            &[&from_keypair, &nonce_keypair],
+            Hash::default(),
+        );
+        assert!(uses_durable_nonce(&tx).is_none());
+    }
+
+    #[test]
+    fn tx_uses_nonce_wrong_first_nonce_ix_fail() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let nonce_keypair = Keypair::new();
+        let nonce_pubkey = nonce_keypair.pubkey();
+        let instructions = [
+            system_instruction::withdraw_nonce_account(
+                &nonce_pubkey,
+                &nonce_pubkey,
+                &from_pubkey,
+                42,
+            ),
+            system_instruction::transfer(&from_pubkey, &nonce_pubkey, 42),
+        ];
+        let message = Message::new(&instructions, Some(&nonce_pubkey));
+        let tx = Transaction::new(&[&from_keypair, &nonce_keypair], message, Hash::default());
+        assert!(uses_durable_nonce(&tx).is_none());
+    }
+
+    #[test]
+    fn tx_validate_durable_nonce_ok() {
+        let (_, _, tx) = nonced_transfer_tx();
+        assert_eq!(tx.validate_durable_nonce(), Ok(()));
+    }
+
+    #[test]
+    fn tx_validate_durable_nonce_empty_ix_fail() {
+        assert_eq!(
+            Transaction::default().validate_durable_nonce(),
+            Err(TransactionError::InvalidNonce)
+        );
+    }
+
+    #[test]
+    fn tx_validate_durable_nonce_bad_prog_id_idx_fail() {
+        let (_, _, mut tx) = nonced_transfer_tx();
+        tx.message.instructions.get_mut(0).unwrap().program_id_index = 255u8;
+        assert_eq!(
+            tx.validate_durable_nonce(),
+            Err(TransactionError::InvalidNonce)
+        );
+    }
+
+    #[test]
+    fn tx_validate_durable_nonce_first_prog_id_not_nonce_fail() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let nonce_keypair = Keypair::new();
+        let nonce_pubkey = nonce_keypair.pubkey();
+        let instructions = [
+            system_instruction::transfer(&from_pubkey, &nonce_pubkey, 42),
+            system_instruction::advance_nonce_account(&nonce_pubkey, &nonce_pubkey),
+        ];
+        let message = Message::new(&instructions, Some(&from_pubkey));
+        let tx = Transaction::new(&[&from_keypair, &nonce_keypair], message, Hash::default());
+        assert_eq!(
+            tx.validate_durable_nonce(),
+            Err(TransactionError::InvalidNonce)
+        );
+    }
+
+    #[test]
+    fn tx_validate_durable_nonce_ro_nonce_account_fail() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let nonce_keypair = Keypair::new();
+        let nonce_pubkey = nonce_keypair.pubkey();
+        let account_metas = vec![
+            AccountMeta::new_readonly(nonce_pubkey, false),
+            #[allow(deprecated)]
+            AccountMeta::new_readonly(sysvar::recent_blockhashes::id(), false),
+            AccountMeta::new_readonly(nonce_pubkey, true),
+        ];
+        let nonce_instruction = Instruction::new_with_bincode(
+            system_program::id(),
+            &system_instruction::SystemInstruction::AdvanceNonceAccount,
+            account_metas,
+        );
+        let tx = Transaction::new_signed_with_payer(
+            &[nonce_instruction],
+            Some(&from_pubkey),
+            &[&from_keypair, &nonce_keypair],
+            Hash::default(),
+        );
+        assert_eq!(
+            tx.validate_durable_nonce(),
+            Err(TransactionError::InvalidNonce)
+        );
+    }
+
+    #[test]
+    fn tx_validate_durable_nonce_wrong_first_nonce_ix_fail() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let nonce_keypair = Keypair::new();
+        let nonce_pubkey = nonce_keypair.pubkey();
+        let instructions = [
+            system_instruction::withdraw_nonce_account(
+                &nonce_pubkey,
+                &nonce_pubkey,
+                &from_pubkey,
+                42,
+            ),
+            system_instruction::transfer(&from_pubkey, &nonce_pubkey, 42),
+        ];
+        let message = Message::new(&instructions, Some(&nonce_pubkey));
+        let tx = Transaction::new(&[&from_keypair, &nonce_keypair], message, Hash::default());
+        assert_eq!(
+            tx.validate_durable_nonce(),
+            Err(TransactionError::InvalidNonce)
+        );
+    }
+
+    #[test]
+    fn get_nonce_pub_from_ix_ok() {
+        let (_, nonce_pubkey, tx) = nonced_transfer_tx();
+        let nonce_ix = uses_durable_nonce(&tx).unwrap();
+        assert_eq!(
+            get_nonce_pubkey_from_instruction(nonce_ix, &tx),
+            Some(&nonce_pubkey),
+        );
+    }
+
+    #[test]
+    fn get_nonce_pub_from_ix_no_accounts_fail() {
+        let (_, _, tx) = nonced_transfer_tx();
+        let nonce_ix = uses_durable_nonce(&tx).unwrap();
+        let mut nonce_ix = nonce_ix.clone();
+        nonce_ix.accounts.clear();
+        assert_eq!(get_nonce_pubkey_from_instruction(&nonce_ix, &tx), None,);
+    }
+
+    #[test]
+    fn get_nonce_pub_from_ix_bad_acc_idx_fail() {
+        let (_, _, tx) = nonced_transfer_tx();
+        let nonce_ix = uses_durable_nonce(&tx).unwrap();
+        let mut nonce_ix = nonce_ix.clone();
+        nonce_ix.accounts[0] = 255u8;
+        assert_eq!(get_nonce_pubkey_from_instruction(&nonce_ix, &tx), None,);
+    }
+
+    #[test]
+    fn tx_keypair_pubkey_mismatch() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let to_pubkey = Pubkey::new_unique();
+        let instructions = [system_instruction::transfer(&from_pubkey, &to_pubkey, 42)];
+        let mut tx = Transaction::new_with_payer(&instructions, Some(&from_pubkey));
+        let unused_keypair = Keypair::new();
+        let err = tx
+            .try_partial_sign(&[&from_keypair, &unused_keypair], Hash::default())
+            .unwrap_err();
+        assert_eq!(err, SignerError::KeypairPubkeyMismatch);
+    }
+
+    #[test]
+    fn test_prepare_for_signing() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let instructions = [system_instruction::transfer(
+            &pubkey,
+            &Pubkey::new_unique(),
+            42,
+        )];
+        let mut tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&pubkey),
+            &[&keypair],
+            Hash::default(),
+        );
+        assert!(tx.is_signed());
+
+        let blockhash = hash(&[1]);
+        let message_data = tx.prepare_for_signing(blockhash);
+        assert_eq!(tx.message.recent_blockhash, blockhash);
+        assert_eq!(tx.signatures, vec![Signature::default()]);
+        assert_eq!(message_data, tx.message_data());
+    }
+
+    #[test]
+    fn test_signers_by_capability() {
+        let payer = Pubkey::new_unique();
+        let writable_signer = Pubkey::new_unique();
+        let readonly_signer = Pubkey::new_unique();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new_readonly(readonly_signer, true),
+                AccountMeta::new(writable_signer, true),
+                AccountMeta::new(Pubkey::new_unique(), false),
+            ],
+        );
+        let tx = Transaction::new_with_payer(&[ix], Some(&payer));
+        assert_eq!(
+            tx.signers_by_capability(),
+            (vec![payer, writable_signer], vec![readonly_signer])
+        );
+    }
+
+    #[test]
+    fn test_verify_bounded() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&keypair0.pubkey()),
+            &[&keypair0, &keypair1],
+            Hash::default(),
+        );
+        assert_eq!(tx.verify_bounded(2), Ok(()));
+        assert_eq!(tx.verify_bounded(1), Err(TransactionError::SanitizeFailure));
+
+        // Signatures within the limit are still verified
+        tx.signatures[1] = Signature::default();
+        assert_eq!(
+            tx.verify_bounded(2),
+            Err(TransactionError::SignatureFailure)
+        );
+    }
+
+    #[test]
+    fn test_blockhash_lifetime_durable_nonce() {
+        let (_, nonce_pubkey, tx) = nonced_transfer_tx();
+        assert_eq!(
+            tx.blockhash_lifetime(),
+            BlockhashLifetime::DurableNonce(nonce_pubkey)
+        );
+    }
+
+    #[test]
+    fn test_blockhash_lifetime_recent_blockhash() {
+        let mut tx = create_sample_transaction();
+        let blockhash = hash(&[1]);
+        tx.message.recent_blockhash = blockhash;
+        assert_eq!(
+            tx.blockhash_lifetime(),
+            BlockhashLifetime::RecentBlockhash(blockhash)
+        );
+    }
+
+    #[test]
+    fn test_instruction_counts_sorted() {
+        let key = Keypair::new();
+        let prog1 = Pubkey::new_unique();
+        let prog2 = Pubkey::new_unique();
+        let prog3 = Pubkey::new_unique();
+        let instructions = vec![
+            CompiledInstruction::new(2, &(), vec![0]),
+            CompiledInstruction::new(1, &(), vec![0]),
+            CompiledInstruction::new(3, &(), vec![0]),
+            CompiledInstruction::new(1, &(), vec![0]),
+        ];
+        let tx = Transaction::new_with_compiled_instructions(
+            &[&key],
+            &[],
+            Hash::default(),
+            vec![prog1, prog2, prog3],
+            instructions,
+        );
+
+        let (first, second) = if prog2 < prog3 {
+            (prog2, prog3)
+        } else {
+            (prog3, prog2)
+        };
+        assert_eq!(
+            tx.instruction_counts_sorted(),
+            vec![(prog1, 2), (first, 1), (second, 1)]
+        );
+    }
+
+    #[test]
+    fn test_validate_instruction_indices() {
+        let key = Keypair::new();
+        let key1 = Pubkey::new_unique();
+        let instructions = vec![
+            CompiledInstruction::new(2, &(), vec![0, 1]),
+            CompiledInstruction::new(2, &(), vec![0, 1]),
+        ];
+        let mut tx = Transaction::new_with_compiled_instructions(
+            &[&key],
+            &[key1],
+            Hash::default(),
+            vec![Pubkey::new_unique()],
+            instructions,
+        );
+        assert_eq!(tx.validate_instruction_indices(), Ok(()));
+
+        tx.message.instructions[0].accounts[1] = 3;
+        tx.message.instructions[1].accounts[0] = 7;
+        assert_eq!(tx.validate_instruction_indices(), Err(vec![(0, 3), (1, 7)]));
+    }
+
+    #[test]
+    fn test_check_instruction_indices() {
+        let key = Keypair::new();
+        let key1 = Pubkey::new_unique();
+        let instructions = vec![
+            CompiledInstruction::new(2, &(), vec![0, 1]),
+            CompiledInstruction::new(2, &(), vec![0, 1]),
+        ];
+        let tx = Transaction::new_with_compiled_instructions(
+            &[&key],
+            &[key1],
+            Hash::default(),
+            vec![Pubkey::new_unique()],
+            instructions,
+        );
+        assert_eq!(tx.check_instruction_indices(), Ok(()));
+
+        let mut bad_program_id_tx = tx.clone();
+        bad_program_id_tx.message.instructions[1].program_id_index = 3;
+        assert_eq!(
+            bad_program_id_tx.check_instruction_indices(),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+
+        let mut bad_account_tx = tx;
+        bad_account_tx.message.instructions[0].accounts[1] = 3;
+        assert_eq!(
+            bad_account_tx.check_instruction_indices(),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+    }
+
+    #[test]
+    fn test_largest_instruction() {
+        assert_eq!(Transaction::default().largest_instruction(), None);
+
+        let key = Keypair::new();
+        let instructions = vec![
+            CompiledInstruction::new(1, &vec![0u8; 4], vec![0]),
+            CompiledInstruction::new(1, &vec![0u8; 16], vec![0]),
+            CompiledInstruction::new(1, &vec![0u8; 8], vec![0]),
+        ];
+        let tx = Transaction::new_with_compiled_instructions(
+            &[&key],
+            &[],
+            Hash::default(),
+            vec![Pubkey::new_unique()],
+            instructions,
+        );
+        let expected_size = serialized_size(&tx.message.instructions[1]).unwrap() as usize;
+        assert_eq!(tx.largest_instruction(), Some((1, expected_size)));
+    }
+
+    #[test]
+    fn test_first_invalid_signer() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let keypair2 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+                AccountMeta::new(keypair2.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&keypair0.pubkey()),
+            &[&keypair0, &keypair1, &keypair2],
+            Hash::default(),
+        );
+        assert_eq!(tx.first_invalid_signer(), None);
+
+        tx.signatures[1] = keypair1.sign_message(&[0]);
+        assert_eq!(tx.first_invalid_signer(), Some(keypair1.pubkey()));
+    }
+
+    #[test]
+    fn test_assemble() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let keypair2 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+                AccountMeta::new(keypair2.pubkey(), true),
+            ],
+        );
+        let message = Message::new(&[ix], Some(&keypair0.pubkey()));
+        let message_data = message.serialize();
+        let signatures = vec![
+            (keypair2.pubkey(), keypair2.sign_message(&message_data)),
+            (keypair0.pubkey(), keypair0.sign_message(&message_data)),
+            (keypair1.pubkey(), keypair1.sign_message(&message_data)),
+        ];
+
+        let tx = Transaction::assemble(message.clone(), &signatures).unwrap();
+        assert_eq!(
+            tx,
+            Transaction::new(
+                &[&keypair0, &keypair1, &keypair2],
+                message.clone(),
+                Hash::default()
+            )
+        );
+        assert_eq!(tx.verify(), Ok(()));
+
+        // Missing signatures leave their slots unsigned
+        let tx = Transaction::assemble(message.clone(), &signatures[..1]).unwrap();
+        assert_eq!(tx.signatures[0], Signature::default());
+        assert_eq!(tx.signatures[2], signatures[0].1);
+
+        // Signatures from non-signers are rejected
+        let stranger = Keypair::new();
+        let signatures = vec![(stranger.pubkey(), stranger.sign_message(&message_data))];
+        assert_eq!(
+            Transaction::assemble(message, &signatures),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+    }
+
+    #[test]
+    fn test_is_noop() {
+        assert!(Transaction::default().is_noop());
+
+        let nonce_keypair = Keypair::new();
+        let nonce_pubkey = nonce_keypair.pubkey();
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::advance_nonce_account(
+                &nonce_pubkey,
+                &nonce_pubkey,
+            )],
+            Some(&nonce_pubkey),
+            &[&nonce_keypair],
+            Hash::default(),
+        );
+        assert!(tx.is_noop());
+
+        let (_, _, tx) = nonced_transfer_tx();
+        assert!(!tx.is_noop());
+
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &from_pubkey,
+                &Pubkey::new_unique(),
+                42,
+            )],
+            Some(&from_pubkey),
+            &[&from_keypair],
+            Hash::default(),
+        );
+        assert!(!tx.is_noop());
+    }
+
+    #[test]
+    fn test_suggested_signing_order() {
+        let payer = Pubkey::new_unique();
+        let writable_signer = Pubkey::new_unique();
+        let readonly_signer = Pubkey::new_unique();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new_readonly(readonly_signer, true),
+                AccountMeta::new(writable_signer, true),
+            ],
+        );
+        let tx = Transaction::new_with_payer(&[ix], Some(&payer));
+        let order = tx.suggested_signing_order();
+        assert_eq!(order[0], payer);
+        assert_eq!(order, vec![payer, writable_signer, readonly_signer]);
+    }
+
+    #[test]
+    fn test_verify_and_sanitize() {
+        let tx = create_sample_transaction();
+        assert_eq!(tx.verify_and_sanitize(), Ok(()));
+
+        let mut unsanitary_tx = tx.clone();
+        unsanitary_tx.message.header.num_required_signatures = 3;
+        assert_eq!(
+            unsanitary_tx.verify_and_sanitize(),
+            Err(TransactionError::SanitizeFailure)
+        );
+
+        let mut unverified_tx = tx;
+        unverified_tx.signatures[0] = Signature::default();
+        assert_eq!(
+            unverified_tx.verify_and_sanitize(),
+            Err(TransactionError::SignatureFailure)
+        );
+    }
+
+    #[test]
+    fn test_replace_instruction() {
+        let recipient = Pubkey::new_unique();
+        let program0 = Pubkey::new_unique();
+        let program1 = Pubkey::new_unique();
+        let program2 = Pubkey::new_unique();
+        let account0 = Pubkey::new_unique();
+        let account1 = Pubkey::new_unique();
+        let account2 = Pubkey::new_unique();
+        let new_signer = Pubkey::new_unique();
+        let instructions = [
+            Instruction::new_with_bincode(program0, &0, vec![AccountMeta::new(account0, false)]),
+            Instruction::new_with_bincode(
+                program1,
+                &1,
+                vec![AccountMeta::new_readonly(account1, false)],
+            ),
+            Instruction::new_with_bincode(
+                program0,
+                &2,
+                vec![AccountMeta::new_readonly(account2, false)],
+            ),
+        ];
+        let keypair = Keypair::new();
+        let mut tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
+        );
+        let replacement = Instruction::new_with_bincode(
+            program2,
+            &3,
+            vec![
+                AccountMeta::new(account2, false),
+                AccountMeta::new_readonly(new_signer, true),
+                AccountMeta::new(recipient, false),
+            ],
+        );
+
+        assert_eq!(
+            tx.replace_instruction(3, replacement.clone()),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+        assert_eq!(tx.replace_instruction(1, replacement), Ok(()));
+        assert_eq!(tx.sanitize(), Ok(()));
+        assert_eq!(tx.signatures, vec![Signature::default(); 2]);
+
+        let message = tx.message();
+        let resolve = |index: usize| {
+            let instruction = &message.instructions[index];
+            let accounts: Vec<Pubkey> = instruction
+                .accounts
+                .iter()
+                .map(|i| message.account_keys[*i as usize])
+                .collect();
+            (
+                message.account_keys[instruction.program_id_index as usize],
+                accounts,
+                instruction.data.clone(),
+            )
+        };
+        assert_eq!(
+            resolve(0),
+            (program0, vec![account0], serialize(&0).unwrap())
+        );
+        assert_eq!(
+            resolve(1),
+            (
+                program2,
+                vec![account2, new_signer, recipient],
+                serialize(&3).unwrap()
+            )
+        );
+        assert_eq!(
+            resolve(2),
+            (program0, vec![account2], serialize(&2).unwrap())
+        );
+
+        // The replaced instruction's orphaned keys are dropped and the new keys
+        // get the permissions they require
+        assert!(!message.account_keys.contains(&account1));
+        assert!(!message.account_keys.contains(&program1));
+        assert_eq!(message.account_keys[0], keypair.pubkey());
+        let new_signer_index = message
+            .account_keys
+            .iter()
+            .position(|key| *key == new_signer)
+            .unwrap();
+        assert!(message.is_signer(new_signer_index));
+        assert!(!message.is_writable(new_signer_index, true));
+        let account2_index = message
+            .account_keys
+            .iter()
+            .position(|key| *key == account2)
+            .unwrap();
+        assert!(message.is_writable(account2_index, true));
+    }
+
+    #[test]
+    fn test_estimated_rent_reserve() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let new_keypair = Keypair::new();
+        let space = 100;
+        let instructions = [
+            system_instruction::create_account(
+                &from_pubkey,
+                &new_keypair.pubkey(),
+                42,
+                space,
+                &Pubkey::new_unique(),
+            ),
+            system_instruction::transfer(&from_pubkey, &Pubkey::new_unique(), 42),
+        ];
+        let tx = Transaction::new_with_payer(&instructions, Some(&from_pubkey));
+        assert_eq!(
+            tx.estimated_rent_reserve(10, 2.0),
+            (crate::rent::ACCOUNT_STORAGE_OVERHEAD + space) * 10 * 2
+        );
+        assert_eq!(
+            create_sample_transaction().estimated_rent_reserve(10, 2.0),
+            0
+        );
+    }
+
+    #[test]
+    fn test_duplicate_instructions() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let to_pubkey = Pubkey::new_unique();
+        let instructions = [
+            system_instruction::transfer(&from_pubkey, &to_pubkey, 42),
+            system_instruction::transfer(&from_pubkey, &to_pubkey, 43),
+            system_instruction::transfer(&from_pubkey, &to_pubkey, 42),
+        ];
+        let tx = Transaction::new_with_payer(&instructions, Some(&from_pubkey));
+        assert_eq!(tx.duplicate_instructions(), vec![(0, 2)]);
+
+        let tx = Transaction::new_with_payer(&instructions[..2], Some(&from_pubkey));
+        assert!(tx.duplicate_instructions().is_empty());
+    }
+
+    #[test]
+    fn test_validate_signature_bounds() {
+        let mut tx = create_sample_transaction();
+        assert_eq!(tx.validate_signature_bounds(), Ok(()));
+
+        tx.signatures
+            .resize(tx.message.account_keys.len() + 1, Signature::default());
+        assert_eq!(
+            tx.validate_signature_bounds(),
+            Err(TransactionError::SignatureCountMismatch {
+                expected: 1,
+                actual: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_base58_round_trip() {
+        let tx = create_sample_transaction();
+        let encoded = tx.encode_base58();
+        assert_eq!(encoded, bs58::encode(serialize(&tx).unwrap()).into_string());
+        assert_eq!(Transaction::decode_base58(&encoded), Ok(tx.clone()));
+
+        let mut bytes = serialize(&tx).unwrap();
+        bytes.push(0);
+        assert_eq!(
+            Transaction::decode_base58(&bs58::encode(&bytes).into_string()),
+            Err(TransactionError::SanitizeFailure)
+        );
+        bytes.truncate(bytes.len() - 2);
+        assert_eq!(
+            Transaction::decode_base58(&bs58::encode(&bytes).into_string()),
+            Err(TransactionError::SanitizeFailure)
+        );
+    }
+
+    #[test]
+    fn test_decode_base58_corrupted() {
+        let mut encoded = create_sample_transaction().encode_base58();
+        encoded.replace_range(..1, "0");
+        assert_eq!(
+            Transaction::decode_base58(&encoded),
+            Err(TransactionError::SanitizeFailure)
+        );
+        assert_eq!(
+            Transaction::decode_base58(""),
+            Err(TransactionError::SanitizeFailure)
+        );
+    }
+
+    #[test]
+    fn test_independent_instruction_groups() {
+        let program_id = Pubkey::new_unique();
+        let account0 = Pubkey::new_unique();
+        let account1 = Pubkey::new_unique();
+        let instructions = [
+            Instruction::new_with_bincode(program_id, &0, vec![AccountMeta::new(account0, false)]),
+            Instruction::new_with_bincode(program_id, &1, vec![AccountMeta::new(account1, false)]),
+            Instruction::new_with_bincode(
+                program_id,
+                &2,
+                vec![AccountMeta::new_readonly(account0, false)],
+            ),
+        ];
+        let tx = Transaction::new_with_payer(&instructions, Some(&Pubkey::new_unique()));
+        assert_eq!(
+            tx.independent_instruction_groups(),
+            vec![vec![0, 1], vec![2]]
+        );
+        assert!(Transaction::default()
+            .independent_instruction_groups()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_verify_slots() {
+        let keypair = Keypair::new();
+        let presigner_keypair = Keypair::new();
+        let presigner_pubkey = presigner_keypair.pubkey();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair.pubkey(), true),
+                AccountMeta::new(presigner_pubkey, true),
+            ],
+        );
+        let message = Message::new(&[ix], Some(&keypair.pubkey()));
+        let mut tx = Transaction::new_unsigned(message);
+
+        // Presigned over a message that was since changed
+        let stale_sig = presigner_keypair.sign_message(&tx.message_data());
+        let blockhash = hash(&[1]);
+        tx.partial_sign(&[&keypair], blockhash);
+        tx.signatures[1] = stale_sig;
+        assert_eq!(
+            tx.verify_slots(&[presigner_pubkey]),
+            Ok(vec![(presigner_pubkey, false)])
+        );
+
+        let fresh_sig = presigner_keypair.sign_message(&tx.message_data());
+        tx.partial_sign(&[&Presigner::new(&presigner_pubkey, &fresh_sig)], blockhash);
+        assert_eq!(
+            tx.verify_slots(&[presigner_pubkey, keypair.pubkey()]),
+            Ok(vec![(presigner_pubkey, true), (keypair.pubkey(), true)])
+        );
+
+        assert_eq!(
+            tx.verify_slots(&[Pubkey::new_unique()]),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+    }
+
+    #[test]
+    fn test_instruction_inclusion_proof() {
+        let (from_pubkey, nonce_pubkey, tx) = nonced_transfer_tx();
+        assert_eq!(tx.instruction_inclusion_proof(2), None);
+
+        let proof = tx.instruction_inclusion_proof(1).unwrap();
+        assert_eq!(proof.fee_payer, tx.message.account_keys[0]);
+        assert_eq!(
+            proof.instruction,
+            serialize(
+                &tx.message
+                    .compile_instruction(&system_instruction::transfer(
+                        &from_pubkey,
+                        &nonce_pubkey,
+                        42
+                    ))
+            )
+            .unwrap()
+        );
+        assert!(proof.verify());
+
+        let mut tampered_proof = proof.clone();
+        tampered_proof.instruction = serialize(&tx.message.instructions[0]).unwrap();
+        assert!(!tampered_proof.verify());
+
+        let mut tampered_proof = proof.clone();
+        tampered_proof.index = 0;
+        assert!(!tampered_proof.verify());
+
+        // Signed by another signer of the message, but not the fee payer
+        let mut forged_proof = proof;
+        forged_proof.fee_payer = from_pubkey;
+        forged_proof.fee_payer_signature = tx.signatures[1];
+        assert!(forged_proof
+            .fee_payer_signature
+            .verify(from_pubkey.as_ref(), &forged_proof.message_data));
+        assert!(!forged_proof.verify());
+    }
+
+    #[test]
+    fn test_validate_distinct_signers() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_with_payer(&[ix], Some(&keypair0.pubkey()));
+        assert_eq!(tx.validate_distinct_signers(), Ok(()));
+
+        tx.message.account_keys[1] = keypair0.pubkey();
+        assert_eq!(tx.validate_distinct_signers(), Err(keypair0.pubkey()));
+    }
+
+    #[test]
+    fn test_writable_accounts_for_program() {
+        let program_id = Pubkey::new_unique();
+        let other_program_id = Pubkey::new_unique();
+        let account0 = Pubkey::new_unique();
+        let account1 = Pubkey::new_unique();
+        let readonly_account = Pubkey::new_unique();
+        let other_account = Pubkey::new_unique();
+        let instructions = [
+            Instruction::new_with_bincode(
+                program_id,
+                &0,
+                vec![
+                    AccountMeta::new(account0, false),
+                    AccountMeta::new_readonly(readonly_account, false),
+                ],
+            ),
+            Instruction::new_with_bincode(
+                other_program_id,
+                &0,
+                vec![AccountMeta::new(other_account, false)],
+            ),
+            Instruction::new_with_bincode(
+                program_id,
+                &0,
+                vec![
+                    AccountMeta::new(account1, false),
+                    AccountMeta::new(account0, false),
+                ],
+            ),
+        ];
+        let tx = Transaction::new_with_payer(&instructions, Some(&Pubkey::new_unique()));
+        assert_eq!(
+            tx.writable_accounts_for_program(&program_id),
+            vec![account0, account1]
+        );
+        assert!(tx
+            .writable_accounts_for_program(&Pubkey::new_unique())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_accounts_for_program() {
+        let program_id = Pubkey::new_unique();
+        let other_program_id = Pubkey::new_unique();
+        let account0 = Pubkey::new_unique();
+        let account1 = Pubkey::new_unique();
+        let other_account = Pubkey::new_unique();
+        let instructions = [
+            Instruction::new_with_bincode(
+                program_id,
+                &0,
+                vec![
+                    AccountMeta::new(account0, false),
+                    AccountMeta::new_readonly(account1, false),
+                    AccountMeta::new(account0, false),
+                ],
+            ),
+            Instruction::new_with_bincode(
+                other_program_id,
+                &0,
+                vec![AccountMeta::new(other_account, false)],
+            ),
+        ];
+        let mut tx = Transaction::new_with_payer(&instructions, Some(&Pubkey::new_unique()));
+        assert_eq!(
+            tx.accounts_for_program(&program_id),
+            vec![&account0, &account1]
+        );
+        assert_eq!(
+            tx.accounts_for_program(&other_program_id),
+            vec![&other_account]
+        );
+        assert!(tx.accounts_for_program(&Pubkey::new_unique()).is_empty());
+
+        tx.message.instructions[0].accounts.push(200);
+        assert_eq!(
+            tx.accounts_for_program(&program_id),
+            vec![&account0, &account1]
+        );
+    }
+
+    #[test]
+    fn test_sanitize_message() {
+        let mut tx = create_sample_transaction();
+        tx.signatures.clear();
+        assert_eq!(tx.sanitize_message(), Ok(()));
+        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+
+        tx.message.instructions[0].accounts[0] = 3;
+        assert_eq!(tx.sanitize_message(), Err(SanitizeError::IndexOutOfBounds));
+    }
+
+    #[test]
+    fn test_ensure_priority_fee() {
+        let mut tx = create_sample_transaction();
+        let payer = tx.message.account_keys[0];
+        let to = tx.message.account_keys[1];
+        let program_id = *get_program_id(&tx, 0);
+        assert!(tx.is_signed());
+
+        assert_eq!(tx.ensure_priority_fee(1_000), Ok(true));
+        assert_eq!(tx.sanitize(), Ok(()));
+        assert!(!tx.is_signed());
+        assert_eq!(tx.message.instructions.len(), 2);
+        assert_eq!(*get_program_id(&tx, 0), compute_budget::id());
+        assert_eq!(
+            try_from_slice_unchecked::<ComputeBudgetInstruction>(
+                tx.instruction_data_at(0).unwrap()
+            )
+            .unwrap(),
+            ComputeBudgetInstruction::SetComputeUnitPrice(1_000)
+        );
+        assert_eq!(*get_program_id(&tx, 1), program_id);
+        assert_eq!(tx.key(1, 0), Some(&payer));
+        assert_eq!(tx.key(1, 1), Some(&to));
+
+        let expected = tx.clone();
+        assert_eq!(tx.ensure_priority_fee(2_000), Ok(false));
+        assert_eq!(tx, expected);
+    }
+
+    #[test]
+    fn test_ensure_priority_fee_durable_nonce() {
+        let (_, nonce_pubkey, mut tx) = nonced_transfer_tx();
+        assert_eq!(tx.ensure_priority_fee(1_000), Ok(true));
+        assert_eq!(tx.sanitize(), Ok(()));
+        assert_eq!(
+            tx.blockhash_lifetime(),
+            BlockhashLifetime::DurableNonce(nonce_pubkey)
+        );
+        assert_eq!(*get_program_id(&tx, 1), compute_budget::id());
+    }
+
+    #[test]
+    fn test_set_compute_unit_limit() {
+        let mut tx = create_sample_transaction();
+        let payer = tx.message.account_keys[0];
+        let to = tx.message.account_keys[1];
+        let program_id = *get_program_id(&tx, 0);
+
+        // Insert path: the compute budget program key is added, shifting indices
+        assert_eq!(tx.set_compute_unit_limit(100_000), Ok(()));
+        assert_eq!(tx.sanitize(), Ok(()));
+        assert_eq!(tx.validate_instruction_indices(), Ok(()));
+        assert!(!tx.is_signed());
+        assert_eq!(tx.message.instructions.len(), 2);
+        assert_eq!(*get_program_id(&tx, 0), compute_budget::id());
+        assert_eq!(*get_program_id(&tx, 1), program_id);
+        assert_eq!(tx.key(1, 0), Some(&payer));
+        assert_eq!(tx.key(1, 1), Some(&to));
+        assert_eq!(tx.requested_compute_unit_limit(), Some(100_000));
+
+        // Update path: the existing instruction is rewritten in place
+        tx.signatures[0] = Signature::new(&[1; 64]);
+        let account_keys = tx.message.account_keys.clone();
+        assert_eq!(tx.set_compute_unit_limit(300_000), Ok(()));
+        assert!(!tx.is_signed());
+        assert_eq!(tx.message.account_keys, account_keys);
+        assert_eq!(tx.message.instructions.len(), 2);
+        assert_eq!(tx.validate_instruction_indices(), Ok(()));
+        assert_eq!(tx.requested_compute_unit_limit(), Some(300_000));
+    }
+
+    #[test]
+    fn test_set_compute_unit_limit_durable_nonce() {
+        let (_, nonce_pubkey, mut tx) = nonced_transfer_tx();
+        assert_eq!(tx.set_compute_unit_limit(100_000), Ok(()));
+        assert_eq!(tx.sanitize(), Ok(()));
+        assert_eq!(
+            tx.blockhash_lifetime(),
+            BlockhashLifetime::DurableNonce(nonce_pubkey)
+        );
+        assert_eq!(*get_program_id(&tx, 1), compute_budget::id());
+        assert_eq!(tx.requested_compute_unit_limit(), Some(100_000));
+    }
+
+    #[test]
+    fn test_account_positions() {
+        let tx = create_sample_transaction();
+        let account_keys = &tx.message.account_keys;
+        let absent = Pubkey::new_unique();
+        assert_eq!(
+            tx.account_positions(&[account_keys[2], absent, account_keys[0]]),
+            vec![Some(2), None, Some(0)]
+        );
+        assert!(tx.account_positions(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_validate_inner_instruction_refs() {
+        let tx = create_sample_transaction();
+        let mut inner = vec![vec![CompiledInstruction::new(2, &(), vec![0, 1])], vec![]];
+        assert_eq!(tx.validate_inner_instruction_refs(&inner), Ok(()));
+
+        inner[1].push(CompiledInstruction::new(2, &(), vec![0, 3]));
+        assert_eq!(
+            tx.validate_inner_instruction_refs(&inner),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+    }
+
+    #[test]
+    fn test_signing_request_round_trip() {
+        let keypair0 = Keypair::new();
+        let offline_keypair = Keypair::new();
+        let offline_pubkey = offline_keypair.pubkey();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(offline_pubkey, true),
+            ],
+        );
+        let blockhash = hash(&[1]);
+        let message = Message::new_with_blockhash(&[ix], Some(&keypair0.pubkey()), &blockhash);
+        let mut tx = Transaction::new_unsigned(message);
+        tx.partial_sign(&[&keypair0], blockhash);
+
+        let request = tx.to_signing_request(&offline_pubkey).unwrap();
+        assert_eq!(request.position, 1);
+        assert_eq!(request.recent_blockhash, blockhash);
+
+        // Transported to and signed by the offline signer
+        let request: SigningRequest = deserialize(&serialize(&request).unwrap()).unwrap();
+        let signature = offline_keypair.sign_message(&request.message_data);
+
+        assert_eq!(
+            tx.apply_signing_response(&offline_pubkey, keypair0.sign_message(&[0])),
+            Err(TransactionError::SignatureFailure)
+        );
+        assert_eq!(
+            tx.apply_signing_response(&offline_pubkey, signature),
+            Ok(())
+        );
+        assert_eq!(
+            tx.verify_slots(&[offline_pubkey]),
+            Ok(vec![(offline_pubkey, true)])
+        );
+        assert_eq!(tx.verify(), Ok(()));
+
+        assert_eq!(
+            tx.to_signing_request(&Pubkey::new_unique()),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+    }
+
+    #[test]
+    fn test_payer_referenced_correctly() {
+        let mut tx = create_sample_transaction();
+        assert!(tx.payer_referenced_correctly());
+
+        let payer = tx.message.account_keys[0];
+        tx.message.account_keys.push(payer);
+        tx.message.instructions[0].accounts[0] = 3;
+        assert!(!tx.payer_referenced_correctly());
+    }
+
+    #[test]
+    fn test_instruction_hashes() {
+        let (_, _, tx) = nonced_transfer_tx();
+        let hashes = tx.instruction_hashes();
+        assert_eq!(hashes.len(), 2);
+        assert_ne!(hashes[0], hashes[1]);
+        assert_eq!(
+            hashes[1],
+            hash(&serialize(&tx.message.instructions[1]).unwrap())
+        );
+        assert_eq!(tx.instruction_hashes(), hashes);
+    }
+
+    #[test]
+    fn test_validate_instruction_shape() {
+        let (_, _, tx) = nonced_transfer_tx();
+        let system_program_id = system_program::id();
+        assert_eq!(
+            tx.validate_instruction_shape(&[system_program_id, system_program_id]),
+            Ok(())
+        );
+        assert_eq!(
+            tx.validate_instruction_shape(&[system_program_id, Pubkey::new_unique()]),
+            Err(TransactionError::InstructionError(
+                1,
+                InstructionError::IncorrectProgramId
+            ))
+        );
+        assert_eq!(
+            tx.validate_instruction_shape(&[system_program_id]),
+            Err(TransactionError::InstructionError(
+                1,
+                InstructionError::IncorrectProgramId
+            ))
+        );
+        assert_eq!(
+            tx.validate_instruction_shape(&[system_program_id; 3]),
+            Err(TransactionError::InstructionError(
+                2,
+                InstructionError::IncorrectProgramId
+            ))
+        );
+    }
+
+    #[test]
+    fn test_blockhash_age() {
+        let mut tx = create_sample_transaction();
+        let blockhash = hash(&[1]);
+        tx.message.recent_blockhash = blockhash;
+        let mut blockhash_slots = HashMap::new();
+        blockhash_slots.insert(blockhash, 10);
+        assert_eq!(tx.blockhash_age(&blockhash_slots, 15), Some(5));
+
+        tx.message.recent_blockhash = hash(&[2]);
+        assert_eq!(tx.blockhash_age(&blockhash_slots, 15), None);
+
+        let (_, _, mut tx) = nonced_transfer_tx();
+        tx.message.recent_blockhash = blockhash;
+        assert_eq!(tx.blockhash_age(&blockhash_slots, 15), None);
+    }
+
+    #[test]
+    fn test_all_involved_pubkeys() {
+        let (from_pubkey, nonce_pubkey, tx) = nonced_transfer_tx();
+        let pubkeys = tx.all_involved_pubkeys();
+        let expected: BTreeSet<Pubkey> = [
+            from_pubkey,
+            nonce_pubkey,
+            system_program::id(),
+            sysvar::recent_blockhashes::id(),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(pubkeys, expected);
+        assert_eq!(pubkeys.len(), tx.message.account_keys.len());
+    }
+
+    #[test]
+    fn test_ingest() {
+        let tx = create_sample_transaction();
+        let bytes = serialize(&tx).unwrap();
+        assert_eq!(
+            Transaction::ingest(&bytes, PACKET_DATA_SIZE),
+            Ok(tx.clone())
+        );
+
+        assert_eq!(
+            Transaction::ingest(&bytes, bytes.len() - 1),
+            Err(TransactionError::SanitizeFailure)
+        );
+        assert_eq!(
+            Transaction::ingest(&bytes[..bytes.len() - 1], PACKET_DATA_SIZE),
+            Err(TransactionError::SanitizeFailure)
+        );
+
+        let mut bad_signature_tx = tx;
+        bad_signature_tx.signatures[0] = Signature::default();
+        assert_eq!(
+            Transaction::ingest(&serialize(&bad_signature_tx).unwrap(), PACKET_DATA_SIZE),
+            Err(TransactionError::SignatureFailure)
+        );
+    }
+
+    #[test]
+    fn test_signature_bitmask() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let keypair2 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+                AccountMeta::new(keypair2.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_with_payer(&[ix], Some(&keypair0.pubkey()));
+        assert_eq!(tx.signature_bitmask(), 0);
+
+        tx.partial_sign(&[&keypair0, &keypair2], Hash::default());
+        assert_eq!(tx.signature_bitmask(), 0b101);
+
+        tx.signatures = vec![Signature::new(&[1; 64]); 65];
+        assert_eq!(tx.signature_bitmask(), u64::MAX);
+    }
+
+    #[test]
+    fn test_set_nonce_authority() {
+        let from_keypair = Keypair::new();
+        let from_pubkey = from_keypair.pubkey();
+        let nonce_pubkey = Pubkey::new_unique();
+        let authority_pubkey = Pubkey::new_unique();
+        let message = Message::new_with_nonce(
+            vec![system_instruction::transfer(
+                &from_pubkey,
+                &Pubkey::new_unique(),
+                42,
+            )],
+            Some(&from_pubkey),
+            &nonce_pubkey,
+            &authority_pubkey,
+        );
+        let mut tx = Transaction::new_unsigned(message);
+        assert_eq!(tx.message.header.num_required_signatures, 2);
+
+        let new_authority = Keypair::new();
+        assert_eq!(tx.set_nonce_authority(new_authority.pubkey()), Ok(()));
+        assert_eq!(tx.sanitize(), Ok(()));
+        assert_eq!(tx.key(0, 2), Some(&new_authority.pubkey()));
+        assert_eq!(tx.signer_key(0, 2), Some(&new_authority.pubkey()));
+        assert_eq!(
+            tx.blockhash_lifetime(),
+            BlockhashLifetime::DurableNonce(nonce_pubkey)
+        );
+        assert_eq!(tx.key(1, 0), Some(&from_pubkey));
+        assert!(!tx.message.account_keys.contains(&authority_pubkey));
+        assert_eq!(tx.message.header.num_required_signatures, 2);
+
+        tx.sign(&[&from_keypair, &new_authority], Hash::default());
+        assert_eq!(tx.verify(), Ok(()));
+
+        let mut tx = create_sample_transaction();
+        assert_eq!(
+            tx.set_nonce_authority(Pubkey::new_unique()),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+    }
+
+    #[test]
+    fn test_instruction_count_for() {
+        let program_id = Pubkey::new_unique();
+        let other_program_id = Pubkey::new_unique();
+        let keypair = Keypair::new();
+        let instructions = [
+            Instruction::new_with_bincode(program_id, &0, vec![]),
+            Instruction::new_with_bincode(other_program_id, &1, vec![]),
+            Instruction::new_with_bincode(program_id, &2, vec![]),
+            Instruction::new_with_bincode(program_id, &3, vec![]),
+        ];
+        let tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
+        );
+        assert_eq!(tx.instruction_count_for(&program_id), 3);
+        assert_eq!(tx.instruction_count_for(&other_program_id), 1);
+        assert_eq!(tx.instruction_count_for(&Pubkey::new_unique()), 0);
+    }
+
+    #[test]
+    fn test_verify_cached() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new_readonly(keypair1.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&keypair0.pubkey()),
+            &[&keypair0, &keypair1],
+            Hash::default(),
+        );
+        let mut cache = HashSet::new();
+        assert_eq!(tx.verify_cached(&mut cache), Ok(()));
+        assert_eq!(cache.len(), 2);
+
+        // Cached entries are trusted without verifying again, so a cache seeded
+        // with an invalid signature shows that no crypto is performed
+        let message_hash = hash(&tx.message_data());
+        let bogus_signature = Signature::new(&[7; 64]);
+        tx.signatures[1] = bogus_signature;
+        let mut seeded_cache = cache.clone();
+        seeded_cache.insert((bogus_signature, keypair1.pubkey(), message_hash));
+        assert_eq!(tx.verify_cached(&mut seeded_cache), Ok(()));
+        assert_eq!(seeded_cache.len(), 3);
+        assert_eq!(
+            tx.verify_cached(&mut cache),
+            Err(TransactionError::SignatureFailure)
+        );
+
+        // Cached signatures are not accepted for a different message
+        tx.sign(&[&keypair0, &keypair1], Hash::default());
+        tx.message.recent_blockhash = hash(&[1]);
+        let mut cache = HashSet::new();
+        cache.insert((tx.signatures[0], keypair0.pubkey(), message_hash));
+        cache.insert((tx.signatures[1], keypair1.pubkey(), message_hash));
+        assert_eq!(
+            tx.verify_cached(&mut cache),
+            Err(TransactionError::SignatureFailure)
+        );
+    }
+
+    #[test]
+    fn test_message_frame() {
+        let keypair = Keypair::new();
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &keypair.pubkey(),
+                &Pubkey::new_unique(),
+                1,
+            )],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
+        );
+        let frame = tx.message_frame();
+        assert_eq!(frame.len(), size_of::<u32>() + tx.message_data().len());
+
+        let mut received = Transaction::default();
+        assert_eq!(received.set_message_from_frame(&frame), Ok(()));
+        assert_eq!(received.message, tx.message);
+        assert_eq!(received.signatures, vec![Signature::default()]);
+        received.sign(&[&keypair], Hash::default());
+        assert_eq!(received, tx);
+    }
+
+    #[test]
+    fn test_message_frame_truncated() {
+        let tx = create_sample_transaction();
+        let frame = tx.message_frame();
+        let mut received = Transaction::default();
+        for len in [0, 2, size_of::<u32>(), frame.len() - 1] {
+            assert_eq!(
+                received.set_message_from_frame(&frame[..len]),
+                Err(TransactionError::SanitizeFailure)
+            );
+        }
+        let mut extended = frame.clone();
+        extended.push(0);
+        assert_eq!(
+            received.set_message_from_frame(&extended),
+            Err(TransactionError::SanitizeFailure)
+        );
+        assert_eq!(received, Transaction::default());
+    }
+
+    #[test]
+    fn test_transaction_error_remediation() {
+        let errors = [
+            TransactionError::AccountInUse,
+            TransactionError::AccountLoadedTwice,
+            TransactionError::AccountNotFound,
+            TransactionError::ProgramAccountNotFound,
+            TransactionError::InsufficientFundsForFee,
+            TransactionError::InvalidAccountForFee,
+            TransactionError::AlreadyProcessed,
+            TransactionError::BlockhashNotFound,
+            TransactionError::InstructionError(0, InstructionError::Custom(1)),
+            TransactionError::CallChainTooDeep,
+            TransactionError::MissingSignatureForFee,
+            TransactionError::InvalidAccountIndex,
+            TransactionError::SignatureFailure,
+            TransactionError::InvalidProgramForExecution,
+            TransactionError::SanitizeFailure,
+            TransactionError::ClusterMaintenance,
+            TransactionError::AccountBorrowOutstanding,
+            TransactionError::WouldExceedMaxBlockCostLimit,
+            TransactionError::UnsupportedVersion,
+            TransactionError::InvalidWritableAccount,
+            TransactionError::WouldExceedMaxAccountCostLimit,
+            TransactionError::InvalidNonce,
+            TransactionError::SignatureCountMismatch {
+                expected: 0,
+                actual: 0,
+            },
+            TransactionError::TooManyAccountLocks,
+        ];
+        let hints: HashSet<&str> = errors.iter().map(|err| err.remediation()).collect();
+        assert!(hints.iter().all(|hint| !hint.is_empty()));
+        assert_eq!(hints.len(), errors.len());
+        assert_eq!(
+            TransactionError::BlockhashNotFound.remediation(),
+            "refresh recent blockhash and re-sign"
+        );
+    }
+
+    #[test]
+    fn test_is_fee_bump_of() {
+        let keypair = Keypair::new();
+        let transfer = system_instruction::transfer(&keypair.pubkey(), &Pubkey::new_unique(), 42);
+        let new_tx = |instructions: &[Instruction], recent_blockhash: Hash| {
+            Transaction::new_signed_with_payer(
+                instructions,
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                recent_blockhash,
+            )
+        };
+        let original = new_tx(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_price(10),
+                transfer.clone(),
+            ],
+            Hash::default(),
+        );
+
+        // A higher price over a fresh blockhash is a valid bump
+        let bump = new_tx(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_price(20),
+                transfer.clone(),
+            ],
+            hash(&[1]),
+        );
+        assert!(bump.is_fee_bump_of(&original));
+        assert!(!original.is_fee_bump_of(&bump));
+        assert!(!original.is_fee_bump_of(&original));
+
+        // Bumping a transaction that didn't set a price
+        let unpriced = new_tx(&[transfer.clone()], Hash::default());
+        assert!(original.is_fee_bump_of(&unpriced));
+        assert!(!unpriced.is_fee_bump_of(&original));
+
+        // A lower price is not a bump
+        let lower = new_tx(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_price(5),
+                transfer.clone(),
+            ],
+            Hash::default(),
+        );
+        assert!(!lower.is_fee_bump_of(&original));
+
+        // Different instructions are not a bump
+        let different = new_tx(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_price(20),
+                system_instruction::transfer(&keypair.pubkey(), &Pubkey::new_unique(), 42),
+            ],
+            Hash::default(),
+        );
+        assert!(!different.is_fee_bump_of(&original));
+    }
+
+    #[test]
+    fn test_has_duplicate_signatures() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let keypair2 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+                AccountMeta::new_readonly(keypair2.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_unsigned(Message::new(&[ix], Some(&keypair0.pubkey())));
+        assert!(!tx.has_duplicate_signatures());
+
+        tx.partial_sign(&[&keypair0], Hash::default());
+        assert!(!tx.has_duplicate_signatures());
+
+        tx.sign(&[&keypair0, &keypair1, &keypair2], Hash::default());
+        assert!(!tx.has_duplicate_signatures());
+
+        tx.signatures[2] = tx.signatures[0];
+        assert!(tx.has_duplicate_signatures());
+    }
+
+    #[test]
+    fn test_packing_key() {
+        let keypair = Keypair::new();
+        let new_tx = |micro_lamports: Option<u64>, lamports: u64| {
+            let mut instructions = vec![system_instruction::transfer(
+                &keypair.pubkey(),
+                &Pubkey::new_unique(),
+                lamports,
+            )];
+            if let Some(micro_lamports) = micro_lamports {
+                instructions.insert(
+                    0,
+                    ComputeBudgetInstruction::set_compute_unit_price(micro_lamports),
+                );
+            }
+            Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        };
+        let unpriced = new_tx(None, 1);
+        assert_eq!(unpriced.packing_key(), (0, unpriced.message.hash()));
+        let high = new_tx(Some(100), 1);
+        assert_eq!(high.packing_key(), (100, high.message.hash()));
+        let tie0 = new_tx(Some(50), 1);
+        let tie1 = new_tx(Some(50), 2);
+
+        let mut txs = vec![&tie0, &unpriced, &tie1, &high];
+        txs.sort_by(|a, b| {
+            let (a_fee, a_hash) = a.packing_key();
+            let (b_fee, b_hash) = b.packing_key();
+            b_fee.cmp(&a_fee).then(a_hash.cmp(&b_hash))
+        });
+        let (first_tie, second_tie) = if tie0.message.hash() < tie1.message.hash() {
+            (&tie0, &tie1)
+        } else {
+            (&tie1, &tie0)
+        };
+        assert_eq!(txs, vec![&high, first_tie, second_tie, &unpriced]);
+    }
+
+    #[test]
+    fn test_reject_extra_signatures() {
+        let keypair = Keypair::new();
+        let mut tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &keypair.pubkey(),
+                &Pubkey::new_unique(),
+                1,
+            )],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
+        );
+        assert_eq!(tx.reject_extra_signatures(), Ok(()));
+
+        tx.signatures.push(Signature::default());
+        assert_eq!(tx.reject_extra_signatures(), Ok(()));
+
+        tx.signatures.push(Signature::new(&[1; 64]));
+        assert_eq!(
+            tx.reject_extra_signatures(),
+            Err(TransactionError::SanitizeFailure)
+        );
+    }
+
+    #[test]
+    fn test_can_add_compute_budget() {
+        let keypair = Keypair::new();
+        let program_id = Pubkey::new_unique();
+        let new_tx = |data_len: usize| {
+            Transaction::new_signed_with_payer(
+                &[Instruction::new_with_bytes(
+                    program_id,
+                    &vec![0; data_len],
+                    vec![AccountMeta::new(keypair.pubkey(), true)],
+                )],
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        };
+        let small_tx = new_tx(0);
+        assert!(small_tx.can_add_compute_budget());
+
+        let base_size = serialized_size(&small_tx).unwrap() as usize;
+        let nearly_full_tx = new_tx(PACKET_DATA_SIZE - base_size - 20);
+        assert!(serialized_size(&nearly_full_tx).unwrap() <= PACKET_DATA_SIZE as u64);
+        assert!(!nearly_full_tx.can_add_compute_budget());
+    }
+
+    #[test]
+    fn test_instruction_data_at() {
+        let program_id = Pubkey::new_unique();
+        let keypair = Keypair::new();
+        let tx = Transaction::new_signed_with_payer(
+            &[
+                Instruction::new_with_bytes(program_id, &[1, 2, 3], vec![]),
+                Instruction::new_with_bytes(program_id, &[], vec![]),
+            ],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
+        );
+        assert_eq!(tx.instruction_data_at(0), Some(&[1, 2, 3][..]));
+        assert_eq!(tx.instruction_data_at(1), Some(&[][..]));
+        assert_eq!(tx.instruction_data_at(2), None);
+        assert_eq!(tx.instruction_data_at(usize::MAX), None);
+    }
+
+    #[test]
+    fn test_signer_completeness() {
+        let valid_keypair = Keypair::new();
+        let invalid_keypair = Keypair::new();
+        let missing_keypair = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(valid_keypair.pubkey(), true),
+                AccountMeta::new_readonly(invalid_keypair.pubkey(), true),
+                AccountMeta::new_readonly(missing_keypair.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_unsigned(Message::new(&[ix], Some(&valid_keypair.pubkey())));
+        tx.partial_sign(&[&valid_keypair], Hash::default());
+        tx.signatures[1] = invalid_keypair.sign_message(b"some other message");
+        let unknown_pubkey = Pubkey::new_unique();
+
+        let completeness = tx.signer_completeness(&[
+            valid_keypair.pubkey(),
+            invalid_keypair.pubkey(),
+            missing_keypair.pubkey(),
+            unknown_pubkey,
+        ]);
+        assert_eq!(
+            completeness.signers,
+            vec![
+                (valid_keypair.pubkey(), SignerStatus::Valid),
+                (invalid_keypair.pubkey(), SignerStatus::Invalid),
+                (missing_keypair.pubkey(), SignerStatus::Missing),
+                (unknown_pubkey, SignerStatus::Missing),
+            ]
+        );
+        assert_eq!(completeness.num_valid(), 1);
+        assert!(!completeness.is_complete());
+
+        tx.partial_sign(&[&invalid_keypair, &missing_keypair], Hash::default());
+        let completeness = tx.signer_completeness(&[
+            valid_keypair.pubkey(),
+            invalid_keypair.pubkey(),
+            missing_keypair.pubkey(),
+        ]);
+        assert_eq!(completeness.num_valid(), 3);
+        assert!(completeness.is_complete());
+    }
+
+    #[test]
+    fn test_serialize_batch_interned() {
+        let payer = Keypair::new();
+        let program_id = Pubkey::new_unique();
+        let shared = Pubkey::new_unique();
+        let txs: Vec<_> = (0..16)
+            .map(|i| {
+                Transaction::new_signed_with_payer(
+                    &[Instruction::new_with_bincode(
+                        program_id,
+                        &i,
+                        vec![
+                            AccountMeta::new(shared, false),
+                            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                        ],
+                    )],
+                    Some(&payer.pubkey()),
+                    &[&payer],
+                    Hash::default(),
+                )
+            })
+            .collect();
+
+        let bytes = serialize_batch_interned(&txs).unwrap();
+        assert_eq!(deserialize_batch_interned(&bytes), Ok(txs.clone()));
+        let concatenated_len: usize = txs.iter().map(|tx| serialize(tx).unwrap().len()).sum();
+        assert!(bytes.len() < concatenated_len);
+
+        assert_eq!(
+            deserialize_batch_interned(&serialize_batch_interned(&[]).unwrap()),
+            Ok(vec![])
+        );
+        assert_eq!(
+            deserialize_batch_interned(&bytes[..bytes.len() - 1]),
+            Err(TransactionError::SanitizeFailure)
+        );
+
+        // More distinct keys than fit in a single byte index
+        let txs: Vec<_> = (0..64)
+            .map(|_| {
+                let instruction = Instruction::new_with_bincode(
+                    Pubkey::new_unique(),
+                    &0,
+                    vec![
+                        AccountMeta::new(Pubkey::new_unique(), false),
+                        AccountMeta::new(Pubkey::new_unique(), false),
+                        AccountMeta::new(Pubkey::new_unique(), false),
+                    ],
+                );
+                Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()))
+            })
+            .collect();
+        let num_distinct_keys = txs
+            .iter()
+            .flat_map(|tx| &tx.message.account_keys)
+            .collect::<HashSet<_>>()
+            .len();
+        assert!(num_distinct_keys > 256);
+        let bytes = serialize_batch_interned(&txs).unwrap();
+        assert_eq!(deserialize_batch_interned(&bytes), Ok(txs));
+    }
+
+    #[test]
+    fn test_deserialize_batch_interned_out_of_bounds() {
+        let tx = create_sample_transaction();
+        let interned_tx = |account_key_indices: &[u16], instructions| InternedTransaction {
+            signatures: tx.signatures.clone(),
+            header: tx.message.header.clone(),
+            account_key_indices: account_key_indices.iter().copied().map(ShortU16).collect(),
+            recent_blockhash: tx.message.recent_blockhash,
+            instructions,
+        };
+        let deserialize = |transactions| {
+            deserialize_batch_interned(
+                &serialize(&InternedBatch {
+                    pubkeys: tx.message.account_keys.clone(),
+                    transactions,
+                })
+                .unwrap(),
+            )
+        };
+
+        assert_eq!(
+            deserialize(vec![interned_tx(
+                &[0, 1, 2],
+                tx.message.instructions.clone()
+            )]),
+            Ok(vec![tx.clone()])
+        );
+
+        // Dictionary index out of bounds
+        assert_eq!(
+            deserialize(vec![interned_tx(
+                &[0, 1, 3],
+                tx.message.instructions.clone()
+            )]),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+
+        // Instruction account index out of bounds
+        let mut instructions = tx.message.instructions.clone();
+        instructions[0].accounts[0] = 3;
+        assert_eq!(
+            deserialize(vec![interned_tx(&[0, 1, 2], instructions)]),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+
+        // Program id index out of bounds
+        let mut instructions = tx.message.instructions.clone();
+        instructions[0].program_id_index = 3;
+        assert_eq!(
+            deserialize(vec![interned_tx(&[0, 1, 2], instructions)]),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+    }
+
+    #[test]
+    fn test_fee_payer() {
+        let payer = Keypair::new();
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &Pubkey::new_unique(),
+                1,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        assert_eq!(tx.fee_payer(), Some(&payer.pubkey()));
+        assert_eq!(
+            VersionedTransaction::from(tx.clone()).fee_payer(),
+            Some(&payer.pubkey())
+        );
+        assert_eq!(
+            SanitizedTransaction::from_transaction_for_tests(tx).fee_payer(),
+            &payer.pubkey()
+        );
+
+        let unsigned_tx = Transaction::new_unsigned(Message::new(
+            &[Instruction::new_with_bincode(
+                Pubkey::new_unique(),
+                &0,
+                vec![AccountMeta::new(Pubkey::new_unique(), false)],
+            )],
+            None,
+        ));
+        assert_eq!(unsigned_tx.message.header.num_required_signatures, 0);
+        assert_eq!(unsigned_tx.fee_payer(), None);
+        assert_eq!(VersionedTransaction::from(unsigned_tx).fee_payer(), None);
+        assert_eq!(Transaction::default().fee_payer(), None);
+    }
+
+    #[test]
+    fn test_verify_with_results_detailed() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new_readonly(keypair1.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_unsigned(Message::new(&[ix], Some(&keypair0.pubkey())));
+        tx.partial_sign(&[&keypair0], Hash::default());
+        assert_eq!(
+            tx.verify_with_results_detailed(),
+            vec![(keypair0.pubkey(), true), (keypair1.pubkey(), false)]
+        );
+
+        tx.partial_sign(&[&keypair1], Hash::default());
+        assert_eq!(
+            tx.verify_with_results_detailed(),
+            vec![(keypair0.pubkey(), true), (keypair1.pubkey(), true)]
+        );
+
+        let num_account_keys = tx.message.account_keys.len();
+        tx.signatures
+            .resize(num_account_keys + 1, Signature::new(&[1; 64]));
+        let results = tx.verify_with_results_detailed();
+        assert_eq!(results.len(), num_account_keys + 1);
+        assert_eq!(results[num_account_keys], (Pubkey::default(), false));
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let txs: Vec<_> = (0..8)
+            .map(|i| {
+                let keypair = Keypair::new();
+                let mut tx = Transaction::new_signed_with_payer(
+                    &[system_instruction::transfer(
+                        &keypair.pubkey(),
+                        &Pubkey::new_unique(),
+                        i,
+                    )],
+                    Some(&keypair.pubkey()),
+                    &[&keypair],
+                    Hash::default(),
+                );
+                if i % 3 == 0 {
+                    tx.signatures[0] = Signature::default();
+                }
+                tx
+            })
+            .collect();
+        let results = verify_batch(&txs);
+        assert_eq!(
+            results,
+            txs.iter().map(|tx| tx.verify()).collect::<Vec<_>>()
+        );
+        assert_eq!(results[0], Err(TransactionError::SignatureFailure));
+        assert_eq!(results[1], Ok(()));
+        assert_eq!(verify_batch(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_serialized_size() {
+        let tx = create_sample_transaction();
+        assert_eq!(
+            tx.serialized_size(),
+            Ok(serialize(&tx).unwrap().len() as u64)
+        );
+        assert!(!tx.is_oversized());
+
+        let keypair = Keypair::new();
+        let new_tx = |data_len: usize| {
+            Transaction::new_signed_with_payer(
+                &[Instruction::new_with_bytes(
+                    Pubkey::new_unique(),
+                    &vec![0; data_len],
+                    vec![],
+                )],
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        };
+        let base_size = new_tx(0).serialized_size().unwrap() as usize;
+        // The data length prefix grows by one byte past 127 bytes of data
+        let full_tx = new_tx(PACKET_DATA_SIZE - base_size - 1);
+        assert_eq!(full_tx.serialized_size(), Ok(PACKET_DATA_SIZE as u64));
+        assert!(!full_tx.is_oversized());
+        let oversized_tx = new_tx(PACKET_DATA_SIZE - base_size);
+        assert_eq!(
+            oversized_tx.serialized_size(),
+            Ok(PACKET_DATA_SIZE as u64 + 1)
+        );
+        assert!(oversized_tx.is_oversized());
+    }
+
+    #[test]
+    fn test_transaction_error_u32_round_trip() {
+        let errors = [
+            TransactionError::AccountInUse,
+            TransactionError::AccountLoadedTwice,
+            TransactionError::AccountNotFound,
+            TransactionError::ProgramAccountNotFound,
+            TransactionError::InsufficientFundsForFee,
+            TransactionError::InvalidAccountForFee,
+            TransactionError::AlreadyProcessed,
+            TransactionError::BlockhashNotFound,
+            TransactionError::CallChainTooDeep,
+            TransactionError::MissingSignatureForFee,
+            TransactionError::InvalidAccountIndex,
+            TransactionError::SignatureFailure,
+            TransactionError::InvalidProgramForExecution,
+            TransactionError::SanitizeFailure,
+            TransactionError::ClusterMaintenance,
+            TransactionError::AccountBorrowOutstanding,
+            TransactionError::WouldExceedMaxBlockCostLimit,
+            TransactionError::UnsupportedVersion,
+            TransactionError::InvalidWritableAccount,
+            TransactionError::WouldExceedMaxAccountCostLimit,
+            TransactionError::InvalidNonce,
+            TransactionError::SignatureCountMismatch {
+                expected: 0,
+                actual: 0,
+            },
+            TransactionError::TooManyAccountLocks,
         ];
-        let tx = Transaction::new_with_compiled_instructions(
-            &[&key],
-            &[key1, key2],
-            Hash::default(),
-            vec![prog1, prog2],
-            instructions,
+        let mut codes = HashSet::new();
+        for err in errors {
+            let code = err.to_u32();
+            assert!(code < INSTRUCTION_ERROR_CODE_FLAG);
+            assert!(codes.insert(code));
+            assert_eq!(TransactionError::from_u32(code), Some(err));
+        }
+        assert_eq!(TransactionError::AccountInUse.to_u32(), 0);
+        assert_eq!(
+            TransactionError::WouldExceedMaxAccountCostLimit.to_u32(),
+            20
+        );
+        assert_eq!(TransactionError::InvalidNonce.to_u32(), 21);
+        assert_eq!(
+            TransactionError::from_u32(21),
+            Some(TransactionError::InvalidNonce)
+        );
+        // The counts aren't encoded
+        assert_eq!(
+            TransactionError::SignatureCountMismatch {
+                expected: 2,
+                actual: 1
+            }
+            .to_u32(),
+            22
+        );
+        assert_eq!(
+            TransactionError::from_u32(22),
+            Some(TransactionError::SignatureCountMismatch {
+                expected: 0,
+                actual: 0
+            })
+        );
+        assert_eq!(TransactionError::TooManyAccountLocks.to_u32(), 23);
+        assert_eq!(
+            TransactionError::from_u32(23),
+            Some(TransactionError::TooManyAccountLocks)
         );
-        assert!(tx.sanitize().is_ok());
 
-        assert_eq!(tx.key(0, 0), Some(&key.pubkey()));
-        assert_eq!(tx.signer_key(0, 0), Some(&key.pubkey()));
+        let instruction_errors = [
+            InstructionError::GenericError,
+            InstructionError::InvalidArgument,
+            InstructionError::InvalidInstructionData,
+            InstructionError::InvalidAccountData,
+            InstructionError::AccountDataTooSmall,
+            InstructionError::InsufficientFunds,
+            InstructionError::IncorrectProgramId,
+            InstructionError::MissingRequiredSignature,
+            InstructionError::AccountAlreadyInitialized,
+            InstructionError::UninitializedAccount,
+            InstructionError::UnbalancedInstruction,
+            InstructionError::ModifiedProgramId,
+            InstructionError::ExternalAccountLamportSpend,
+            InstructionError::ExternalAccountDataModified,
+            InstructionError::ReadonlyLamportChange,
+            InstructionError::ReadonlyDataModified,
+            InstructionError::DuplicateAccountIndex,
+            InstructionError::ExecutableModified,
+            InstructionError::RentEpochModified,
+            InstructionError::NotEnoughAccountKeys,
+            InstructionError::AccountDataSizeChanged,
+            InstructionError::AccountNotExecutable,
+            InstructionError::AccountBorrowFailed,
+            InstructionError::AccountBorrowOutstanding,
+            InstructionError::DuplicateAccountOutOfSync,
+            InstructionError::Custom(0),
+            InstructionError::InvalidError,
+            InstructionError::ExecutableDataModified,
+            InstructionError::ExecutableLamportChange,
+            InstructionError::ExecutableAccountNotRentExempt,
+            InstructionError::UnsupportedProgramId,
+            InstructionError::CallDepth,
+            InstructionError::MissingAccount,
+            InstructionError::ReentrancyNotAllowed,
+            InstructionError::MaxSeedLengthExceeded,
+            InstructionError::InvalidSeeds,
+            InstructionError::InvalidRealloc,
+            InstructionError::ComputationalBudgetExceeded,
+            InstructionError::PrivilegeEscalation,
+            InstructionError::ProgramEnvironmentSetupFailure,
+            InstructionError::ProgramFailedToComplete,
+            InstructionError::ProgramFailedToCompile,
+            InstructionError::Immutable,
+            InstructionError::IncorrectAuthority,
+            InstructionError::BorshIoError(String::new()),
+            InstructionError::AccountNotRentExempt,
+            InstructionError::InvalidAccountOwner,
+            InstructionError::ArithmeticOverflow,
+            InstructionError::UnsupportedSysvar,
+            InstructionError::IllegalOwner,
+        ];
+        let num_kinds = instruction_errors.len() as u32;
+        for (kind, instruction_error) in instruction_errors.into_iter().enumerate() {
+            // Kinds are listed in declaration order, which is also the order
+            // bincode numbers the variants in
+            assert_eq!(
+                serialize(&instruction_error).unwrap()[..4],
+                (kind as u32).to_le_bytes()
+            );
+            for index in [0, 3, u8::MAX] {
+                let err = TransactionError::InstructionError(index, instruction_error.clone());
+                let code = err.to_u32();
+                assert_eq!(
+                    code,
+                    INSTRUCTION_ERROR_CODE_FLAG | (kind as u32) << 8 | index as u32
+                );
+                assert!(codes.insert(code));
+                assert_eq!(TransactionError::from_u32(code), Some(err));
+            }
+        }
+        // Every kind of instruction error is listed
+        assert!(deserialize::<InstructionError>(&num_kinds.to_le_bytes()).is_err());
 
-        assert_eq!(tx.key(1, 0), Some(&key.pubkey()));
-        assert_eq!(tx.signer_key(1, 0), Some(&key.pubkey()));
+        assert_eq!(
+            TransactionError::InstructionError(1, InstructionError::Custom(42)).to_u32(),
+            TransactionError::InstructionError(1, InstructionError::Custom(0)).to_u32()
+        );
+        assert_eq!(TransactionError::from_u32(8), None);
+        assert_eq!(TransactionError::from_u32(24), None);
+        assert_eq!(
+            TransactionError::from_u32(INSTRUCTION_ERROR_CODE_FLAG | num_kinds << 8),
+            None
+        );
+    }
 
-        assert_eq!(tx.key(0, 1), Some(&key1));
-        assert_eq!(tx.signer_key(0, 1), None);
+    #[test]
+    fn test_get_durable_nonce_pubkey() {
+        let (_, nonce_pubkey, tx) = nonced_transfer_tx();
+        assert_eq!(tx.get_durable_nonce_pubkey(), Some(&nonce_pubkey));
 
-        assert_eq!(tx.key(1, 1), Some(&key2));
-        assert_eq!(tx.signer_key(1, 1), None);
+        assert_eq!(create_sample_transaction().get_durable_nonce_pubkey(), None);
 
-        assert_eq!(tx.key(2, 0), None);
-        assert_eq!(tx.signer_key(2, 0), None);
+        // A nonce advance instruction without accounts
+        let mut tx_no_accounts = tx.clone();
+        tx_no_accounts.message.instructions[0].accounts.clear();
+        assert_eq!(tx_no_accounts.get_durable_nonce_pubkey(), None);
 
-        assert_eq!(tx.key(0, 2), None);
-        assert_eq!(tx.signer_key(0, 2), None);
+        // A nonce account index past the end of the account keys
+        let mut tx_bad_index = tx;
+        tx_bad_index.message.instructions[0].accounts[0] = u8::MAX;
+        assert_eq!(tx_bad_index.get_durable_nonce_pubkey(), None);
+    }
 
-        assert_eq!(*get_program_id(&tx, 0), prog1);
-        assert_eq!(*get_program_id(&tx, 1), prog2);
+    #[test]
+    fn test_try_partial_sign_prehashed() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new_readonly(keypair1.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_unsigned(Message::new(&[ix], Some(&keypair0.pubkey())));
+        tx.partial_sign(&[&keypair0], Hash::default());
+        let signature0 = tx.signatures[0];
+
+        let prehashed = hash(&tx.message_data());
+        tx.try_partial_sign_prehashed(&[&keypair1], vec![1], prehashed, Hash::default())
+            .unwrap();
+        assert_eq!(tx.signatures[0], signature0);
+        assert!(tx.signatures[1].verify(keypair1.pubkey().as_ref(), prehashed.as_ref()));
+        assert_eq!(tx.verify_with_results(), vec![true, false]);
+
+        // A new blockhash clears the existing signatures
+        let recent_blockhash = hash(&[1]);
+        let mut message = tx.message.clone();
+        message.recent_blockhash = recent_blockhash;
+        let prehashed = hash(&message.serialize());
+        tx.try_partial_sign_prehashed(&[&keypair1], vec![1], prehashed, recent_blockhash)
+            .unwrap();
+        assert_eq!(tx.message.recent_blockhash, recent_blockhash);
+        assert_eq!(tx.signatures[0], Signature::default());
+        assert!(tx.signatures[1].verify(keypair1.pubkey().as_ref(), prehashed.as_ref()));
     }
 
     #[test]
-    fn test_refs_invalid_program_id() {
-        let key = Keypair::new();
-        let instructions = vec![CompiledInstruction::new(1, &(), vec![])];
-        let tx = Transaction::new_with_compiled_instructions(
-            &[&key],
-            &[],
+    fn test_verify_full() {
+        let feature_set = Arc::new(feature_set::FeatureSet::all_enabled());
+        let keypair = Keypair::new();
+        let transfer = system_instruction::transfer(&keypair.pubkey(), &Pubkey::new_unique(), 1);
+        let mut tx = Transaction::new_signed_with_payer(
+            &[transfer.clone()],
+            Some(&keypair.pubkey()),
+            &[&keypair],
             Hash::default(),
-            vec![],
-            instructions,
         );
+        assert_eq!(tx.verify_full(&feature_set), Ok(()));
+
+        tx.signatures[0] = Signature::default();
+        assert_eq!(
+            tx.verify_full(&feature_set),
+            Err(TransactionError::SignatureFailure)
+        );
+
+        // Valid signatures but a malformed ed25519 precompile instruction
+        let broken_precompile =
+            Instruction::new_with_bytes(crate::ed25519_program::id(), &[0xff], vec![]);
+        let tx = Transaction::new_signed_with_payer(
+            &[broken_precompile, transfer],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
+        );
+        assert_eq!(tx.verify(), Ok(()));
+        assert_eq!(
+            tx.verify_full(&feature_set),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+        assert_eq!(
+            tx.verify_full(&feature_set),
+            tx.verify_precompiles(&feature_set)
+        );
+    }
+
+    #[test]
+    fn test_instructions_iter() {
+        let mut tx = create_sample_transaction();
+        let payer = tx.message.account_keys[0];
+        let to = tx.message.account_keys[1];
+        let program_id = tx.message.account_keys[2];
+        let data = tx.message.instructions[0].data.clone();
+        assert_eq!(
+            tx.instructions_iter().collect::<Vec<_>>(),
+            vec![(&program_id, vec![&payer, &to], data.as_slice())]
+        );
+
+        tx.message
+            .instructions
+            .push(CompiledInstruction::new(1, &(), vec![0, 200, 2]));
+        tx.message
+            .instructions
+            .push(CompiledInstruction::new(100, &(), vec![0]));
+        let instructions: Vec<_> = tx.instructions_iter().collect();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[1].0, &to);
+        assert_eq!(instructions[1].1, vec![&payer, &program_id]);
+    }
+
+    #[test]
+    fn test_base64_round_trip() {
+        let tx = create_sample_transaction();
+        let encoded = tx.encode_base64();
+        assert_eq!(encoded, base64::encode(serialize(&tx).unwrap()));
+        assert_eq!(Transaction::decode_base64(&encoded), Ok(tx.clone()));
+
+        let mut bytes = serialize(&tx).unwrap();
+        bytes.push(0);
+        assert_eq!(
+            Transaction::decode_base64(&base64::encode(&bytes)),
+            Err(TransactionError::SanitizeFailure)
+        );
+        assert_eq!(
+            Transaction::decode_base64("not base64!"),
+            Err(TransactionError::SanitizeFailure)
+        );
+    }
+
+    #[test]
+    fn test_decode_unsanitized() {
+        let mut tx = create_sample_transaction();
+        tx.message.instructions[0].program_id_index = 100;
         assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        assert_eq!(
+            Transaction::decode_base64(&tx.encode_base64()),
+            Err(TransactionError::SanitizeFailure)
+        );
+        assert_eq!(
+            Transaction::decode_base58(&tx.encode_base58()),
+            Err(TransactionError::SanitizeFailure)
+        );
+    }
+
+    #[test]
+    fn test_requested_compute_unit_limit() {
+        let keypair = Keypair::new();
+        let new_tx = |instructions: &[Instruction]| {
+            Transaction::new_signed_with_payer(
+                instructions,
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        };
+        let transfer = system_instruction::transfer(&keypair.pubkey(), &Pubkey::new_unique(), 1);
+
+        let tx = new_tx(&[transfer.clone()]);
+        assert_eq!(tx.requested_compute_unit_limit(), None);
+
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::request_heap_frame(64 * 1024),
+            transfer.clone(),
+        ]);
+        assert_eq!(tx.requested_compute_unit_limit(), None);
+
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::request_units(100_000),
+            transfer.clone(),
+        ]);
+        assert_eq!(tx.requested_compute_unit_limit(), Some(100_000));
+
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::request_units(100_000),
+            transfer,
+            ComputeBudgetInstruction::request_units(200_000),
+        ]);
+        assert_eq!(tx.requested_compute_unit_limit(), Some(200_000));
+    }
+
+    #[test]
+    fn test_requested_compute_unit_price() {
+        let keypair = Keypair::new();
+        let new_tx = |instructions: &[Instruction]| {
+            Transaction::new_signed_with_payer(
+                instructions,
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        };
+        let transfer = system_instruction::transfer(&keypair.pubkey(), &Pubkey::new_unique(), 1);
+
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::request_units(100_000),
+            transfer.clone(),
+        ]);
+        assert_eq!(tx.requested_compute_unit_price(), None);
+
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::request_units(100_000),
+            ComputeBudgetInstruction::set_compute_unit_price(5_000),
+            transfer.clone(),
+        ]);
+        assert_eq!(tx.requested_compute_unit_price(), Some(5_000));
+        assert_eq!(tx.requested_compute_unit_limit(), Some(100_000));
+
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::set_compute_unit_price(5_000),
+            ComputeBudgetInstruction::set_compute_unit_price(7_000),
+            transfer,
+        ]);
+        assert_eq!(tx.requested_compute_unit_price(), Some(7_000));
+    }
+
+    #[test]
+    fn test_try_partial_sign_preserving() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new_readonly(keypair1.pubkey(), true),
+            ],
+        );
+        let mut tx = Transaction::new_unsigned(Message::new(&[ix], Some(&keypair0.pubkey())));
+        tx.sign(&[&keypair0, &keypair1], Hash::default());
+        let stale_signature = tx.signatures[1];
+
+        let recent_blockhash = hash(&[1]);
+        tx.try_partial_sign_preserving(&[&keypair0], vec![0], recent_blockhash)
+            .unwrap();
+        assert_eq!(tx.message.recent_blockhash, recent_blockhash);
+        assert_eq!(tx.signatures[1], stale_signature);
+        assert_eq!(tx.verify_with_results(), vec![true, false]);
+
+        tx.try_partial_sign_preserving(&[&keypair1], vec![1], recent_blockhash)
+            .unwrap();
+        assert_eq!(tx.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_account_counts() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new_readonly(keypair1.pubkey(), true),
+                AccountMeta::new(Pubkey::new_unique(), false),
+                AccountMeta::new(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            ],
+        );
+        let mut tx = Transaction::new_unsigned(Message::new(&[ix], Some(&keypair0.pubkey())));
+        // The program id is a readonly unsigned account too
+        assert_eq!(tx.message.account_keys.len(), 6);
+        assert_eq!(tx.num_signers(), 2);
+        assert_eq!(tx.num_writable_signed(), 1);
+        assert_eq!(tx.num_writable_unsigned(), 2);
+        assert_eq!(tx.num_readonly(), 3);
+
+        tx.message.header.num_required_signatures = 10;
+        tx.message.header.num_readonly_signed_accounts = 20;
+        tx.message.header.num_readonly_unsigned_accounts = 30;
+        assert_eq!(tx.num_signers(), 6);
+        assert_eq!(tx.num_writable_signed(), 0);
+        assert_eq!(tx.num_writable_unsigned(), 0);
+        assert_eq!(tx.num_readonly(), 6);
+
+        let tx = Transaction::default();
+        assert_eq!(tx.num_signers(), 0);
+        assert_eq!(tx.num_writable_signed(), 0);
+        assert_eq!(tx.num_writable_unsigned(), 0);
+        assert_eq!(tx.num_readonly(), 0);
+    }
+
+    #[test]
+    fn test_set_recent_blockhash() {
+        let mut tx = create_sample_transaction();
+        let signatures = tx.signatures.clone();
+        assert!(tx.is_signed());
+
+        tx.set_recent_blockhash(tx.message.recent_blockhash);
+        assert_eq!(tx.signatures, signatures);
+
+        let blockhash = hash(&[1]);
+        tx.set_recent_blockhash(blockhash);
+        assert_eq!(tx.message.recent_blockhash, blockhash);
+        assert_eq!(tx.signatures, vec![Signature::default(); signatures.len()]);
+    }
+
+    #[test]
+    fn test_try_from_transaction_for_tests() {
+        let tx = create_sample_transaction();
+        assert_eq!(
+            SanitizedTransaction::try_from_transaction_for_tests(tx.clone())
+                .unwrap()
+                .message_hash(),
+            &tx.message.hash()
+        );
+
+        let mut duplicate_key_tx = tx.clone();
+        duplicate_key_tx.message.account_keys[1] = duplicate_key_tx.message.account_keys[0];
+        assert_eq!(
+            SanitizedTransaction::try_from_transaction_for_tests(duplicate_key_tx).unwrap_err(),
+            SanitizeTransactionError::DuplicateAccountKey
+        );
+
+        let mut payer_program_tx = tx.clone();
+        payer_program_tx.message.instructions[0].program_id_index = 0;
+        assert_eq!(
+            SanitizedTransaction::try_from_transaction_for_tests(payer_program_tx).unwrap_err(),
+            SanitizeTransactionError::InvalidProgramIndex
+        );
+
+        let mut missing_program_tx = tx.clone();
+        missing_program_tx.message.instructions[0].program_id_index = 3;
+        assert_eq!(
+            SanitizedTransaction::try_from_transaction_for_tests(missing_program_tx).unwrap_err(),
+            SanitizeTransactionError::InvalidProgramIndex
+        );
+
+        let mut bad_account_tx = tx.clone();
+        bad_account_tx.message.instructions[0].accounts[0] = 3;
+        assert_eq!(
+            SanitizedTransaction::try_from_transaction_for_tests(bad_account_tx).unwrap_err(),
+            SanitizeTransactionError::IndexOutOfBounds
+        );
+
+        let mut missing_signature_tx = tx;
+        missing_signature_tx.signatures.clear();
+        assert_eq!(
+            SanitizedTransaction::try_from_transaction_for_tests(missing_signature_tx).unwrap_err(),
+            SanitizeTransactionError::IndexOutOfBounds
+        );
+    }
+
+    #[test]
+    fn test_count_identical_instructions() {
+        let program_id = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let keypair = Keypair::new();
+        let new_tx = |instructions: &[Instruction]| {
+            Transaction::new_signed_with_payer(
+                instructions,
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        };
+        assert_eq!(new_tx(&[]).count_identical_instructions(), 0);
+
+        let distinct = [
+            Instruction::new_with_bincode(program_id, &0, vec![AccountMeta::new(account, false)]),
+            Instruction::new_with_bincode(program_id, &1, vec![AccountMeta::new(account, false)]),
+            Instruction::new_with_bincode(program_id, &0, vec![]),
+        ];
+        assert_eq!(new_tx(&distinct).count_identical_instructions(), 1);
+
+        let mut instructions = distinct.to_vec();
+        instructions.extend(vec![distinct[1].clone(); 2]);
+        instructions.push(distinct[2].clone());
+        assert_eq!(new_tx(&instructions).count_identical_instructions(), 3);
+    }
+
+    #[test]
+    fn test_clone_unsigned_with_blockhash() {
+        let tx = create_sample_transaction();
+        let original = tx.clone();
+        let blockhash = hash(&[1]);
+
+        let retry_tx = tx.clone_unsigned_with_blockhash(blockhash);
+        assert_eq!(tx, original);
+        assert!(tx.is_signed());
+        assert_eq!(retry_tx.message.recent_blockhash, blockhash);
+        assert_eq!(retry_tx.message.instructions, tx.message.instructions);
+        assert_eq!(retry_tx.message.account_keys, tx.message.account_keys);
+        assert_eq!(
+            retry_tx.signatures,
+            vec![Signature::default(); tx.signatures.len()]
+        );
+    }
+
+    #[test]
+    fn test_transaction_error_instruction_error_accessors() {
+        let err = TransactionError::InstructionError(3, InstructionError::Custom(7));
+        assert_eq!(err.instruction_index(), Some(3));
+        assert_eq!(
+            err.as_instruction_error(),
+            Some(&InstructionError::Custom(7))
+        );
+
+        for err in [
+            TransactionError::BlockhashNotFound,
+            TransactionError::SignatureFailure,
+        ] {
+            assert_eq!(err.instruction_index(), None);
+            assert_eq!(err.as_instruction_error(), None);
+        }
+    }
+
+    #[test]
+    fn test_message_hash() {
+        let mut tx = create_sample_transaction();
+        let message_hash = tx.message_hash();
+        assert_eq!(tx.verify_and_hash_message(), Ok(message_hash));
+        assert_eq!(message_hash, tx.message.hash());
+
+        tx.signatures[0] = Signature::default();
+        assert_eq!(
+            tx.verify_and_hash_message(),
+            Err(TransactionError::SignatureFailure)
+        );
+        assert_eq!(tx.message_hash(), message_hash);
+    }
+
+    #[test]
+    fn test_into_versioned_transaction() {
+        let tx = create_sample_transaction();
+        let signatures_ptr = tx.signatures.as_ptr();
+        let legacy_bytes = serialize(&tx).unwrap();
+
+        let versioned_tx = VersionedTransaction::from(tx.clone());
+        assert_eq!(serialize(&versioned_tx).unwrap(), legacy_bytes);
+        assert_eq!(versioned_tx.into_legacy_transaction(), Some(tx.clone()));
+
+        // The signatures are moved rather than copied
+        let versioned_tx: VersionedTransaction = tx.into();
+        assert_eq!(versioned_tx.signatures.as_ptr(), signatures_ptr);
+        assert_eq!(
+            deserialize::<VersionedTransaction>(&legacy_bytes).unwrap(),
+            versioned_tx
+        );
+    }
+
+    #[test]
+    fn test_sanitize_with_feature_set() {
+        let keypair = Keypair::new();
+        let new_tx = |num_accounts: usize| {
+            let accounts = (0..num_accounts)
+                .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+                .collect();
+            Transaction::new_signed_with_payer(
+                &[Instruction::new_with_bincode(
+                    Pubkey::new_unique(),
+                    &0,
+                    accounts,
+                )],
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        };
+        let feature_inactive = Arc::new(feature_set::FeatureSet::default());
+        let mut feature_active = feature_set::FeatureSet::default();
+        feature_active.activate(&feature_set::limit_tx_account_keys::id(), 0);
+        let feature_active = Arc::new(feature_active);
+
+        // The payer and program id are account keys too
+        let tx = new_tx(MAX_TX_ACCOUNT_KEYS - 2);
+        assert_eq!(tx.message.account_keys.len(), MAX_TX_ACCOUNT_KEYS);
+        assert_eq!(tx.sanitize_with_feature_set(&feature_inactive), Ok(()));
+        assert_eq!(tx.sanitize_with_feature_set(&feature_active), Ok(()));
+
+        let tx = new_tx(MAX_TX_ACCOUNT_KEYS - 1);
+        assert_eq!(tx.sanitize(), Ok(()));
+        assert_eq!(tx.sanitize_with_feature_set(&feature_inactive), Ok(()));
+        assert_eq!(
+            tx.sanitize_with_feature_set(&feature_active),
+            Err(TransactionError::TooManyAccountLocks)
+        );
+
+        let mut tx = new_tx(1);
+        tx.message.instructions[0].program_id_index = 0;
+        assert_eq!(
+            tx.sanitize_with_feature_set(&feature_inactive),
+            Err(TransactionError::SanitizeFailure)
+        );
+    }
+
+    #[test]
+    fn test_try_partial_sign_pubkey_failure() {
+        struct DisconnectedSigner;
+        impl Signer for DisconnectedSigner {
+            fn try_pubkey(&self) -> result::Result<Pubkey, SignerError> {
+                Err(SignerError::Connection("device unplugged".to_string()))
+            }
+            fn try_sign_message(&self, _message: &[u8]) -> result::Result<Signature, SignerError> {
+                Err(SignerError::Connection("device unplugged".to_string()))
+            }
+            fn is_interactive(&self) -> bool {
+                true
+            }
+        }
+
+        let keypair = Keypair::new();
+        let mut tx = Transaction::new_with_payer(
+            &[system_instruction::transfer(
+                &keypair.pubkey(),
+                &Pubkey::new_unique(),
+                1,
+            )],
+            Some(&keypair.pubkey()),
+        );
+        let signers: [&dyn Signer; 2] = [&keypair, &DisconnectedSigner];
+        assert_eq!(
+            tx.try_partial_sign(&signers, Hash::default()),
+            Err(SignerError::Connection("device unplugged".to_string()))
+        );
+        assert!(!tx.is_signed());
+    }
+
+    #[test]
+    fn test_unique_account_keys() {
+        let mut tx = create_sample_transaction();
+        assert_eq!(tx.unique_account_keys(), tx.message.account_keys);
+
+        let payer = tx.message.account_keys[0];
+        let program_id = tx.message.account_keys[2];
+        tx.message.account_keys[1] = payer;
+        tx.message.account_keys.push(program_id);
+        tx.message.account_keys.push(payer);
+        assert_eq!(tx.message.account_keys.len(), 5);
+        assert_eq!(tx.unique_account_keys(), vec![payer, program_id]);
     }
+
     #[test]
-    fn test_refs_invalid_account() {
-        let key = Keypair::new();
-        let instructions = vec![CompiledInstruction::new(1, &(), vec![2])];
-        let tx = Transaction::new_with_compiled_instructions(
-            &[&key],
-            &[],
-            Hash::default(),
-            vec![Pubkey::default()],
-            instructions,
+    fn test_transaction_verification_mode_run() {
+        let feature_set = Arc::new(feature_set::FeatureSet::all_enabled());
+        let keypair = Keypair::new();
+        let transfer = system_instruction::transfer(&keypair.pubkey(), &Pubkey::new_unique(), 1);
+        let broken_precompile =
+            Instruction::new_with_bytes(crate::ed25519_program::id(), &[0xff], vec![]);
+        let new_tx = |instructions: &[Instruction]| {
+            Transaction::new_signed_with_payer(
+                instructions,
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                Hash::default(),
+            )
+        };
+        let modes = [
+            TransactionVerificationMode::HashOnly,
+            TransactionVerificationMode::HashAndVerifyPrecompiles,
+            TransactionVerificationMode::FullVerification,
+        ];
+
+        let tx = new_tx(&[transfer.clone()]);
+        for mode in modes {
+            assert_eq!(mode.run(&tx, &feature_set), Ok(Some(tx.message_hash())));
+        }
+
+        let mut unsigned_tx = tx.clone();
+        unsigned_tx.signatures[0] = Signature::default();
+        let results: Vec<_> = modes
+            .iter()
+            .map(|mode| mode.run(&unsigned_tx, &feature_set))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                Ok(Some(tx.message_hash())),
+                Ok(Some(tx.message_hash())),
+                Err(TransactionError::SignatureFailure),
+            ]
+        );
+
+        let tx = new_tx(&[broken_precompile, transfer]);
+        let results: Vec<_> = modes
+            .iter()
+            .map(|mode| mode.run(&tx, &feature_set))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                Ok(Some(tx.message_hash())),
+                Err(TransactionError::InvalidAccountIndex),
+                Err(TransactionError::InvalidAccountIndex),
+            ]
         );
-        assert_eq!(*get_program_id(&tx, 0), Pubkey::default());
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
     }
 
     #[test]
-    fn test_sanitize_txs() {
-        let key = Keypair::new();
-        let id0 = Pubkey::default();
-        let program_id = solana_sdk::pubkey::new_rand();
-        let ix = Instruction::new_with_bincode(
-            program_id,
+    fn test_calculate_fee() {
+        let payer = Keypair::new();
+        let signer1 = Keypair::new();
+        let signer2 = Keypair::new();
+        let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let multisig = Instruction::new_with_bincode(
+            Pubkey::new_unique(),
             &0,
             vec![
-                AccountMeta::new(key.pubkey(), true),
-                AccountMeta::new(id0, true),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(signer1.pubkey(), true),
+                AccountMeta::new_readonly(signer2.pubkey(), true),
             ],
         );
-        let mut tx = Transaction::new_with_payer(&[ix], Some(&key.pubkey()));
-        let o = tx.clone();
-        assert_eq!(tx.sanitize(), Ok(()));
-        assert_eq!(tx.message.account_keys.len(), 3);
-
-        tx = o.clone();
-        tx.message.header.num_required_signatures = 3;
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        let new_tx = |instructions: &[Instruction]| {
+            Transaction::new_with_payer(instructions, Some(&payer.pubkey()))
+        };
 
-        tx = o.clone();
-        tx.message.header.num_readonly_signed_accounts = 4;
-        tx.message.header.num_readonly_unsigned_accounts = 0;
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        let tx = new_tx(&[transfer.clone()]);
+        assert_eq!(tx.calculate_fee(5_000), 5_000);
 
-        tx = o.clone();
-        tx.message.header.num_readonly_signed_accounts = 2;
-        tx.message.header.num_readonly_unsigned_accounts = 2;
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        let tx = new_tx(&[multisig.clone()]);
+        assert_eq!(tx.calculate_fee(5_000), 15_000);
 
-        tx = o.clone();
-        tx.message.header.num_readonly_signed_accounts = 0;
-        tx.message.header.num_readonly_unsigned_accounts = 4;
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        // Price over the default compute unit limit
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::set_compute_unit_price(10),
+            transfer.clone(),
+        ]);
+        assert_eq!(tx.calculate_fee(5_000), 5_002);
 
-        tx = o.clone();
-        tx.message.instructions[0].program_id_index = 3;
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        // Price over the requested compute unit limit
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::request_units(300_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000),
+            multisig,
+        ]);
+        assert_eq!(tx.calculate_fee(5_000), 15_300);
 
-        tx = o.clone();
-        tx.message.instructions[0].accounts[0] = 3;
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        // Partial lamports are rounded up
+        let tx = new_tx(&[
+            ComputeBudgetInstruction::request_units(1),
+            ComputeBudgetInstruction::set_compute_unit_price(1),
+            transfer,
+        ]);
+        assert_eq!(tx.calculate_fee(5_000), 5_001);
+    }
 
-        tx = o.clone();
-        tx.message.instructions[0].program_id_index = 0;
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+    #[test]
+    fn test_signature() {
+        let tx = create_sample_transaction();
+        assert_eq!(tx.signature(), &tx.signatures[0]);
+        assert_eq!(tx.try_signature(), Some(&tx.signatures[0]));
 
-        tx = o.clone();
-        tx.message.header.num_readonly_signed_accounts = 2;
-        tx.message.header.num_readonly_unsigned_accounts = 3;
-        tx.message.account_keys.resize(4, Pubkey::default());
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        let unsigned_tx = Transaction::new_unsigned(tx.message.clone());
+        assert_eq!(unsigned_tx.signature(), &Signature::default());
+        assert_eq!(unsigned_tx.try_signature(), Some(&Signature::default()));
 
-        tx = o;
-        tx.message.header.num_readonly_signed_accounts = 2;
-        tx.message.header.num_required_signatures = 1;
-        assert_eq!(tx.sanitize(), Err(SanitizeError::IndexOutOfBounds));
+        assert_eq!(Transaction::default().try_signature(), None);
     }
 
-    fn create_sample_transaction() -> Transaction {
-        let keypair = Keypair::from_bytes(&[
-            48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32, 255, 101, 36, 24, 124, 23,
-            167, 21, 132, 204, 155, 5, 185, 58, 121, 75, 156, 227, 116, 193, 215, 38, 142, 22, 8,
-            14, 229, 239, 119, 93, 5, 218, 161, 35, 3, 33, 0, 36, 100, 158, 252, 33, 161, 97, 185,
-            62, 89, 99,
-        ])
-        .unwrap();
-        let to = Pubkey::new(&[
-            1, 1, 1, 4, 5, 6, 7, 8, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 8, 7, 6, 5, 4,
-            1, 1, 1,
-        ]);
+    #[test]
+    #[should_panic]
+    fn test_signature_without_signatures() {
+        Transaction::default().signature();
+    }
 
-        let program_id = Pubkey::new(&[
-            2, 2, 2, 4, 5, 6, 7, 8, 9, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 9, 8, 7, 6, 5, 4,
-            2, 2, 2,
-        ]);
-        let account_metas = vec![
-            AccountMeta::new(keypair.pubkey(), true),
-            AccountMeta::new(to, false),
+    #[test]
+    fn test_program_ids() {
+        let payer = Pubkey::new_unique();
+        let program_a = Pubkey::new_unique();
+        let program_b = Pubkey::new_unique();
+        let instructions = [
+            Instruction::new_with_bincode(program_a, &(), vec![AccountMeta::new(payer, true)]),
+            Instruction::new_with_bincode(program_b, &(), vec![]),
+            Instruction::new_with_bincode(program_a, &(), vec![]),
         ];
-        let instruction =
-            Instruction::new_with_bincode(program_id, &(1u8, 2u8, 3u8), account_metas);
-        let message = Message::new(&[instruction], Some(&keypair.pubkey()));
-        Transaction::new(&[&keypair], message, Hash::default())
+        let mut tx = Transaction::new_unsigned(Message::new(&instructions, Some(&payer)));
+        assert_eq!(tx.program_ids(), vec![&program_a, &program_b]);
+
+        tx.message
+            .instructions
+            .push(CompiledInstruction::new(100, &(), vec![0]));
+        assert_eq!(tx.program_ids(), vec![&program_a, &program_b]);
+        assert!(Transaction::default().program_ids().is_empty());
     }
 
     #[test]
-    fn test_transaction_serialize() {
-        let tx = create_sample_transaction();
-        let ser = serialize(&tx).unwrap();
-        let deser = deserialize(&ser).unwrap();
-        assert_eq!(tx, deser);
+    fn test_is_simple_vote_transaction() {
+        let voter = Keypair::new();
+        let vote_instruction = Instruction::new_with_bincode(
+            crate::vote::program::id(),
+            &(),
+            vec![AccountMeta::new(voter.pubkey(), true)],
+        );
+        let tx = Transaction::new_signed_with_payer(
+            &[vote_instruction.clone()],
+            Some(&voter.pubkey()),
+            &[&voter],
+            Hash::default(),
+        );
+        assert!(tx.is_simple_vote_transaction());
+
+        let mut wrong_program = vote_instruction.clone();
+        wrong_program.program_id = Pubkey::new_unique();
+        let tx = Transaction::new_signed_with_payer(
+            &[wrong_program],
+            Some(&voter.pubkey()),
+            &[&voter],
+            Hash::default(),
+        );
+        assert!(!tx.is_simple_vote_transaction());
+
+        let tx = Transaction::new_signed_with_payer(
+            &[vote_instruction.clone(), vote_instruction.clone()],
+            Some(&voter.pubkey()),
+            &[&voter],
+            Hash::default(),
+        );
+        assert!(!tx.is_simple_vote_transaction());
+
+        let payer = Keypair::new();
+        let tx = Transaction::new_signed_with_payer(
+            &[vote_instruction],
+            Some(&payer.pubkey()),
+            &[&payer, &voter],
+            Hash::default(),
+        );
+        assert!(!tx.is_simple_vote_transaction());
     }
 
-    /// Detect changes to the serialized size of payment transactions, which affects TPS.
     #[test]
-    fn test_transaction_minimum_serialized_size() {
-        let alice_keypair = Keypair::new();
-        let alice_pubkey = alice_keypair.pubkey();
-        let bob_pubkey = solana_sdk::pubkey::new_rand();
-        let ix = system_instruction::transfer(&alice_pubkey, &bob_pubkey, 42);
+    fn test_from_message_and_signatures() {
+        let tx = create_sample_transaction();
+        assert_eq!(
+            Transaction::from_message_and_signatures(tx.message.clone(), tx.signatures.clone()),
+            Ok(tx.clone())
+        );
+        assert_eq!(
+            Transaction::from_message_and_signatures(tx.message.clone(), vec![]),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+        assert_eq!(
+            Transaction::from_message_and_signatures(
+                tx.message.clone(),
+                vec![Signature::default(); 2]
+            ),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+    }
 
-        let expected_data_size = size_of::<u32>() + size_of::<u64>();
-        assert_eq!(expected_data_size, 12);
+    #[test]
+    fn test_resolved_instruction() {
+        let mut tx = create_sample_transaction();
+        let payer = tx.message.account_keys[0];
+        let to = tx.message.account_keys[1];
+        let program_id = tx.message.account_keys[2];
         assert_eq!(
-            ix.data.len(),
-            expected_data_size,
-            "unexpected system instruction size"
+            tx.resolved_instruction(0),
+            Some(ResolvedInstruction {
+                program_id: &program_id,
+                accounts: vec![&payer, &to],
+                data: &tx.message.instructions[0].data,
+            })
         );
+        assert_eq!(tx.resolved_instruction(1), None);
 
-        let expected_instruction_size = 1 + 1 + ix.accounts.len() + 1 + expected_data_size;
-        assert_eq!(expected_instruction_size, 17);
+        tx.message
+            .instructions
+            .push(CompiledInstruction::new(1, &(), vec![0, 200]));
+        tx.message
+            .instructions
+            .push(CompiledInstruction::new(100, &(), vec![0]));
+        assert_eq!(tx.resolved_instruction(1), None);
+        assert_eq!(tx.resolved_instruction(2), None);
+    }
 
-        let message = Message::new(&[ix], Some(&alice_pubkey));
+    #[test]
+    fn test_verify_with_results_and_hash() {
+        let mut tx = create_sample_transaction();
         assert_eq!(
-            serialized_size(&message.instructions[0]).unwrap() as usize,
-            expected_instruction_size,
-            "unexpected Instruction::serialized_size"
+            tx.verify_with_results_and_hash(),
+            (
+                tx.verify_with_results(),
+                tx.verify_and_hash_message().unwrap()
+            )
         );
 
-        let tx = Transaction::new(&[&alice_keypair], message, Hash::default());
+        tx.signatures[0] = Signature::default();
+        let (results, hash) = tx.verify_with_results_and_hash();
+        assert_eq!(results, vec![false]);
+        assert_eq!(results, tx.verify_with_results());
+        assert_eq!(hash, tx.message_hash());
+    }
 
-        let len_size = 1;
-        let num_required_sigs_size = 1;
-        let num_readonly_accounts_size = 2;
-        let blockhash_size = size_of::<Hash>();
-        let expected_transaction_size = len_size
-            + (tx.signatures.len() * size_of::<Signature>())
-            + num_required_sigs_size
-            + num_readonly_accounts_size
-            + len_size
-            + (tx.message.account_keys.len() * size_of::<Pubkey>())
-            + blockhash_size
-            + len_size
-            + expected_instruction_size;
-        assert_eq!(expected_transaction_size, 215);
+    #[test]
+    fn test_verify_signatures_len_strict() {
+        let mut tx = create_sample_transaction();
+        assert_eq!(tx.verify_signatures_len_strict(), Ok(()));
 
+        tx.signatures.push(Signature::default());
         assert_eq!(
-            serialized_size(&tx).unwrap() as usize,
-            expected_transaction_size,
-            "unexpected serialized transaction size"
+            tx.verify_signatures_len_strict(),
+            Err(TransactionError::SignatureCountMismatch {
+                expected: 1,
+                actual: 2,
+            })
+        );
+
+        tx.signatures.clear();
+        assert_eq!(
+            tx.verify_signatures_len_strict(),
+            Err(TransactionError::SignatureCountMismatch {
+                expected: 1,
+                actual: 0,
+            })
         );
     }
 
-    /// Detect binary changes in the serialized transaction data, which could have a downstream
-    /// affect on SDKs and applications
     #[test]
-    fn test_sdk_serialize() {
-        assert_eq!(
-            serialize(&create_sample_transaction()).unwrap(),
+    fn test_packet_count() {
+        let keypair = Keypair::new();
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &keypair.pubkey(),
+                &Pubkey::new_unique(),
+                42,
+            )],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
+        );
+        assert_eq!(tx.packet_count(PACKET_DATA_SIZE), Ok(1));
+        let size = tx.serialized_size().unwrap() as usize;
+        assert_eq!(tx.packet_count(size), Ok(1));
+        assert_eq!(tx.packet_count(size - 1), Ok(2));
+
+        let large_tx = Transaction::new_signed_with_payer(
+            &[Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &vec![0; 2 * PACKET_DATA_SIZE],
+                vec![],
+            )],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
+        );
+        assert!(large_tx.is_oversized());
+        assert_eq!(large_tx.packet_count(PACKET_DATA_SIZE), Ok(3));
+    }
+
+    #[test]
+    fn test_writable_and_readonly_account_keys() {
+        let payer = Keypair::new();
+        let readonly_signer = Keypair::new();
+        let writable = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let instruction = Instruction::new_with_bincode(
+            program_id,
+            &(),
             vec![
-                1, 71, 59, 9, 187, 190, 129, 150, 165, 21, 33, 158, 72, 87, 110, 144, 120, 79, 238,
-                132, 134, 105, 39, 102, 116, 209, 29, 229, 154, 36, 105, 44, 172, 118, 131, 22,
-                124, 131, 179, 142, 176, 27, 117, 160, 89, 102, 224, 204, 1, 252, 141, 2, 136, 0,
-                37, 218, 225, 129, 92, 154, 250, 59, 97, 178, 10, 1, 0, 1, 3, 156, 227, 116, 193,
-                215, 38, 142, 22, 8, 14, 229, 239, 119, 93, 5, 218, 161, 35, 3, 33, 0, 36, 100,
-                158, 252, 33, 161, 97, 185, 62, 89, 99, 1, 1, 1, 4, 5, 6, 7, 8, 9, 9, 9, 9, 9, 9,
-                9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 8, 7, 6, 5, 4, 1, 1, 1, 2, 2, 2, 4, 5, 6, 7, 8, 9, 1,
-                1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 9, 8, 7, 6, 5, 4, 2, 2, 2, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2,
-                2, 0, 1, 3, 1, 2, 3
-            ]
+                AccountMeta::new_readonly(readonly_signer.pubkey(), true),
+                AccountMeta::new(writable, false),
+                AccountMeta::new_readonly(readonly, false),
+                AccountMeta::new(sysvar::clock::id(), false),
+            ],
+        );
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer, &readonly_signer],
+            Hash::default(),
+        );
+
+        let writable_keys = tx.writable_account_keys();
+        let readonly_keys = tx.readonly_account_keys();
+        assert_eq!(writable_keys, vec![&payer.pubkey(), &writable]);
+        assert!(readonly_keys.contains(&&readonly_signer.pubkey()));
+        assert!(readonly_keys.contains(&&readonly));
+        assert!(readonly_keys.contains(&&program_id));
+        // Sysvars are demoted to readonly even if requested as writable
+        assert!(readonly_keys.contains(&&sysvar::clock::id()));
+
+        assert!(writable_keys.iter().all(|key| !readonly_keys.contains(key)));
+        assert_eq!(
+            writable_keys.len() + readonly_keys.len(),
+            tx.message.account_keys.len()
         );
     }
 
     #[test]
-    #[should_panic]
-    fn test_transaction_missing_key() {
-        let keypair = Keypair::new();
-        let message = Message::new(&[], None);
-        Transaction::new_unsigned(message).sign(&[&keypair], Hash::default());
+    fn test_try_new_unsigned() {
+        let tx = create_sample_transaction();
+        assert_eq!(
+            Transaction::try_new_unsigned(tx.message.clone()),
+            Ok(Transaction::new_unsigned(tx.message.clone()))
+        );
+
+        let mut message = tx.message;
+        message.header.num_required_signatures = message.account_keys.len() as u8 + 1;
+        assert_eq!(
+            Transaction::try_new_unsigned(message),
+            Err(TransactionError::SanitizeFailure)
+        );
     }
 
     #[test]
-    #[should_panic]
-    fn test_partial_sign_mismatched_key() {
-        let keypair = Keypair::new();
-        let fee_payer = solana_sdk::pubkey::new_rand();
-        let ix = Instruction::new_with_bincode(
-            Pubkey::default(),
-            &0,
-            vec![AccountMeta::new(fee_payer, true)],
+    fn test_framed_round_trip() {
+        let tx0 = create_sample_transaction();
+        let (_, _, tx1) = nonced_transfer_tx();
+        let mut buf = vec![];
+        tx0.write_framed(&mut buf).unwrap();
+        tx1.write_framed(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..4],
+            &(tx0.serialized_size().unwrap() as u32).to_le_bytes()
+        );
+
+        let mut cursor = io::Cursor::new(&buf);
+        assert_eq!(Transaction::read_framed(&mut cursor), Ok(tx0));
+        assert_eq!(Transaction::read_framed(&mut cursor), Ok(tx1));
+        assert_eq!(
+            Transaction::read_framed(&mut cursor),
+            Err(TransactionError::SanitizeFailure)
+        );
+
+        // Truncated frame
+        let mut cursor = io::Cursor::new(&buf[..buf.len() - 1]);
+        Transaction::read_framed(&mut cursor).unwrap();
+        assert_eq!(
+            Transaction::read_framed(&mut cursor),
+            Err(TransactionError::SanitizeFailure)
+        );
+
+        // Oversized length prefix
+        let mut cursor = io::Cursor::new((PACKET_DATA_SIZE as u32 + 1).to_le_bytes());
+        assert_eq!(
+            Transaction::read_framed(&mut cursor),
+            Err(TransactionError::SanitizeFailure)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let tx = create_sample_transaction();
+        let payer = tx.message.account_keys[0];
+        let program_id = tx.message.account_keys[2];
+        let display = tx.to_string();
+        assert!(display.contains(&payer.to_string()));
+        assert_eq!(
+            display,
+            format!(
+                "Fee payer: {}\nSignatures: 1\nRecent blockhash: {}\n\
+                 Instruction 0: program {}, 2 accounts, 3 bytes of data\n",
+                payer,
+                Hash::default(),
+                program_id
+            )
+        );
+
+        assert_eq!(
+            Transaction::default().to_string(),
+            format!(
+                "Fee payer: none\nSignatures: 0\nRecent blockhash: {}\n",
+                Hash::default()
+            )
         );
-        let message = Message::new(&[ix], Some(&fee_payer));
-        Transaction::new_unsigned(message).partial_sign(&[&keypair], Hash::default());
     }
 
     #[test]
-    fn test_partial_sign() {
+    fn test_signed_pubkeys() {
         let keypair0 = Keypair::new();
         let keypair1 = Keypair::new();
         let keypair2 = Keypair::new();
         let ix = Instruction::new_with_bincode(
-            Pubkey::default(),
+            Pubkey::new_unique(),
             &0,
             vec![
                 AccountMeta::new(keypair0.pubkey(), true),
                 AccountMeta::new(keypair1.pubkey(), true),
-                AccountMeta::new(keypair2.pubkey(), true),
+                AccountMeta::new_readonly(keypair2.pubkey(), true),
             ],
         );
-        let message = Message::new(&[ix], Some(&keypair0.pubkey()));
-        let mut tx = Transaction::new_unsigned(message);
+        let mut tx = Transaction::new_unsigned(Message::new(&[ix], Some(&keypair0.pubkey())));
+        assert!(tx.signed_pubkeys().is_empty());
 
         tx.partial_sign(&[&keypair0, &keypair2], Hash::default());
-        assert!(!tx.is_signed());
-        tx.partial_sign(&[&keypair1], Hash::default());
-        assert!(tx.is_signed());
-
-        let hash = hash(&[1]);
-        tx.partial_sign(&[&keypair1], hash);
-        assert!(!tx.is_signed());
-        tx.partial_sign(&[&keypair0, &keypair2], hash);
-        assert!(tx.is_signed());
-    }
-
-    #[test]
-    #[should_panic]
-    fn test_transaction_missing_keypair() {
-        let program_id = Pubkey::default();
-        let keypair0 = Keypair::new();
-        let id0 = keypair0.pubkey();
-        let ix = Instruction::new_with_bincode(program_id, &0, vec![AccountMeta::new(id0, true)]);
-        let message = Message::new(&[ix], Some(&id0));
-        Transaction::new_unsigned(message).sign(&Vec::<&Keypair>::new(), Hash::default());
-    }
-
-    #[test]
-    #[should_panic]
-    fn test_transaction_wrong_key() {
-        let program_id = Pubkey::default();
-        let keypair0 = Keypair::new();
-        let wrong_id = Pubkey::default();
-        let ix =
-            Instruction::new_with_bincode(program_id, &0, vec![AccountMeta::new(wrong_id, true)]);
-        let message = Message::new(&[ix], Some(&wrong_id));
-        Transaction::new_unsigned(message).sign(&[&keypair0], Hash::default());
-    }
+        assert_eq!(
+            tx.signed_pubkeys(),
+            vec![&keypair0.pubkey(), &keypair2.pubkey()]
+        );
 
-    #[test]
-    fn test_transaction_correct_key() {
-        let program_id = Pubkey::default();
-        let keypair0 = Keypair::new();
-        let id0 = keypair0.pubkey();
-        let ix = Instruction::new_with_bincode(program_id, &0, vec![AccountMeta::new(id0, true)]);
-        let message = Message::new(&[ix], Some(&id0));
-        let mut tx = Transaction::new_unsigned(message);
-        tx.sign(&[&keypair0], Hash::default());
+        tx.partial_sign(&[&keypair1], Hash::default());
         assert_eq!(
-            tx.message.instructions[0],
-            CompiledInstruction::new(1, &0, vec![0])
+            tx.signed_pubkeys(),
+            vec![&keypair0.pubkey(), &keypair1.pubkey(), &keypair2.pubkey()]
         );
-        assert!(tx.is_signed());
     }
 
     #[test]
-    fn test_transaction_instruction_with_duplicate_keys() {
-        let program_id = Pubkey::default();
+    fn test_unsigned_pubkeys() {
         let keypair0 = Keypair::new();
-        let id0 = keypair0.pubkey();
-        let id1 = solana_sdk::pubkey::new_rand();
+        let keypair1 = Keypair::new();
+        let keypair2 = Keypair::new();
         let ix = Instruction::new_with_bincode(
-            program_id,
+            Pubkey::new_unique(),
             &0,
             vec![
-                AccountMeta::new(id0, true),
-                AccountMeta::new(id1, false),
-                AccountMeta::new(id0, false),
-                AccountMeta::new(id1, false),
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+                AccountMeta::new_readonly(keypair2.pubkey(), true),
+                AccountMeta::new(Pubkey::new_unique(), false),
             ],
         );
-        let message = Message::new(&[ix], Some(&id0));
-        let mut tx = Transaction::new_unsigned(message);
-        tx.sign(&[&keypair0], Hash::default());
+        let mut tx = Transaction::new_unsigned(Message::new(&[ix], Some(&keypair0.pubkey())));
         assert_eq!(
-            tx.message.instructions[0],
-            CompiledInstruction::new(2, &0, vec![0, 1, 0, 1])
+            tx.unsigned_pubkeys(),
+            vec![&keypair0.pubkey(), &keypair1.pubkey(), &keypair2.pubkey()]
         );
-        assert!(tx.is_signed());
+
+        tx.partial_sign(&[&keypair1], Hash::default());
+        assert_eq!(
+            tx.unsigned_pubkeys(),
+            vec![&keypair0.pubkey(), &keypair2.pubkey()]
+        );
+
+        tx.partial_sign(&[&keypair0, &keypair2], Hash::default());
+        assert!(tx.unsigned_pubkeys().is_empty());
     }
 
     #[test]
-    fn test_try_sign_dyn_keypairs() {
-        let program_id = Pubkey::default();
+    fn test_verify_precompiles_detailed() {
+        let feature_set = Arc::new(feature_set::FeatureSet::all_enabled());
         let keypair = Keypair::new();
-        let pubkey = keypair.pubkey();
-        let presigner_keypair = Keypair::new();
-        let presigner_pubkey = presigner_keypair.pubkey();
-
-        let ix = Instruction::new_with_bincode(
-            program_id,
-            &0,
-            vec![
-                AccountMeta::new(pubkey, true),
-                AccountMeta::new(presigner_pubkey, true),
-            ],
+        let transfer = system_instruction::transfer(&keypair.pubkey(), &Pubkey::new_unique(), 1);
+        let tx = Transaction::new_signed_with_payer(
+            &[transfer.clone()],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
         );
-        let message = Message::new(&[ix], Some(&pubkey));
-        let mut tx = Transaction::new_unsigned(message);
-
-        let presigner_sig = presigner_keypair.sign_message(&tx.message_data());
-        let presigner = Presigner::new(&presigner_pubkey, &presigner_sig);
-
-        let signers: Vec<&dyn Signer> = vec![&keypair, &presigner];
-
-        let res = tx.try_sign(&signers, Hash::default());
-        assert_eq!(res, Ok(()));
-        assert_eq!(tx.signatures[0], keypair.sign_message(&tx.message_data()));
-        assert_eq!(tx.signatures[1], presigner_sig);
+        assert_eq!(tx.verify_precompiles_detailed(&feature_set), Ok(()));
 
-        // Wrong key should error, not panic
-        let another_pubkey = solana_sdk::pubkey::new_rand();
-        let ix = Instruction::new_with_bincode(
-            program_id,
-            &0,
-            vec![
-                AccountMeta::new(another_pubkey, true),
-                AccountMeta::new(presigner_pubkey, true),
-            ],
+        let broken_precompile =
+            Instruction::new_with_bytes(crate::ed25519_program::id(), &[0xff], vec![]);
+        let mut tx = Transaction::new_signed_with_payer(
+            &[transfer, broken_precompile],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default(),
         );
-        let message = Message::new(&[ix], Some(&another_pubkey));
-        let mut tx = Transaction::new_unsigned(message);
-
-        let res = tx.try_sign(&signers, Hash::default());
-        assert!(res.is_err());
         assert_eq!(
-            tx.signatures,
-            vec![Signature::default(), Signature::default()]
+            tx.verify_precompiles_detailed(&feature_set),
+            Err((1, TransactionError::InvalidAccountIndex))
+        );
+        assert_eq!(
+            tx.verify_precompiles(&feature_set),
+            Err(TransactionError::InvalidAccountIndex)
         );
-    }
-
-    fn nonced_transfer_tx() -> (Pubkey, Pubkey, Transaction) {
-        let from_keypair = Keypair::new();
-        let from_pubkey = from_keypair.pubkey();
-        let nonce_keypair = Keypair::new();
-        let nonce_pubkey = nonce_keypair.pubkey();
-        let instructions = [
-            system_instruction::advance_nonce_account(&nonce_pubkey, &nonce_pubkey),
-            system_instruction::transfer(&from_pubkey, &nonce_pubkey, 42),
-        ];
-        let message = Message::new(&instructions, Some(&nonce_pubkey));
-        let tx = Transaction::new(&[&from_keypair, &nonce_keypair], message, Hash::default());
-        (from_pubkey, nonce_pubkey, tx)
-    }
 
-    #[test]
-    fn tx_uses_nonce_ok() {
-        let (_, _, tx) = nonced_transfer_tx();
-        assert!(uses_durable_nonce(&tx).is_some());
+        tx.message.instructions[0].program_id_index = 100;
+        assert_eq!(
+            tx.verify_precompiles_detailed(&feature_set),
+            Err((0, TransactionError::AccountNotFound))
+        );
     }
 
     #[test]
-    fn tx_uses_nonce_empty_ix_fail() {
-        assert!(uses_durable_nonce(&Transaction::default()).is_none());
-    }
+    fn test_max_referenced_account_index() {
+        let mut tx = create_sample_transaction();
+        // The program id is the last account key
+        assert_eq!(tx.max_referenced_account_index(), Some(2));
 
-    #[test]
-    fn tx_uses_nonce_bad_prog_id_idx_fail() {
-        let (_, _, mut tx) = nonced_transfer_tx();
-        tx.message.instructions.get_mut(0).unwrap().program_id_index = 255u8;
-        assert!(uses_durable_nonce(&tx).is_none());
-    }
+        tx.message
+            .instructions
+            .push(CompiledInstruction::new(1, &(), vec![0, 5]));
+        assert_eq!(tx.max_referenced_account_index(), Some(5));
 
-    #[test]
-    fn tx_uses_nonce_first_prog_id_not_nonce_fail() {
-        let from_keypair = Keypair::new();
-        let from_pubkey = from_keypair.pubkey();
-        let nonce_keypair = Keypair::new();
-        let nonce_pubkey = nonce_keypair.pubkey();
-        let instructions = [
-            system_instruction::transfer(&from_pubkey, &nonce_pubkey, 42),
-            system_instruction::advance_nonce_account(&nonce_pubkey, &nonce_pubkey),
-        ];
-        let message = Message::new(&instructions, Some(&from_pubkey));
-        let tx = Transaction::new(&[&from_keypair, &nonce_keypair], message, Hash::default());
-        assert!(uses_durable_nonce(&tx).is_none());
-    }
+        tx.message
+            .instructions
+            .push(CompiledInstruction::new(200, &(), vec![]));
+        assert_eq!(tx.max_referenced_account_index(), Some(200));
 
-    #[test]
-    fn tx_uses_ro_nonce_account() {
-        let from_keypair = Keypair::new();
-        let from_pubkey = from_keypair.pubkey();
-        let nonce_keypair = Keypair::new();
-        let nonce_pubkey = nonce_keypair.pubkey();
-        let account_metas = vec![
-            AccountMeta::new_readonly(nonce_pubkey, false),
-            #[allow(deprecated)]
-            AccountMeta::new_readonly(sysvar::recent_blockhashes::id(), false),
-            AccountMeta::new_readonly(nonce_pubkey, true),
-        ];
-        let nonce_instruction = Instruction::new_with_bincode(
-            system_program::id(),
-            &system_instruction::SystemInstruction::AdvanceNonceAccount,
-            account_metas,
-        );
-        let tx = Transaction::new_signed_with_payer(
-            &[nonce_instruction],
-            Some(&from_pubkey),
-            &[&from_keypair, &nonce_keypair],
-            Hash::default(),
-        );
-        assert!(uses_durable_nonce(&tx).is_none());
+        assert_eq!(Transaction::default().max_referenced_account_index(), None);
     }
 
     #[test]
-    fn tx_uses_nonce_wrong_first_nonce_ix_fail() {
-        let from_keypair = Keypair::new();
-        let from_pubkey = from_keypair.pubkey();
-        let nonce_keypair = Keypair::new();
-        let nonce_pubkey = nonce_keypair.pubkey();
+    fn test_total_instruction_data_len() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
         let instructions = [
-            system_instruction::withdraw_nonce_account(
-                &nonce_pubkey,
-                &nonce_pubkey,
-                &from_pubkey,
-                42,
-            ),
-            system_instruction::transfer(&from_pubkey, &nonce_pubkey, 42),
+            Instruction::new_with_bytes(program_id, &[1, 2, 3], vec![]),
+            Instruction::new_with_bytes(program_id, &[0; 10], vec![]),
         ];
-        let message = Message::new(&instructions, Some(&nonce_pubkey));
-        let tx = Transaction::new(&[&from_keypair, &nonce_keypair], message, Hash::default());
-        assert!(uses_durable_nonce(&tx).is_none());
+        let tx = Transaction::new_with_payer(&instructions, Some(&payer));
+        assert_eq!(tx.total_instruction_data_len(), 13);
+        assert_eq!(Transaction::default().total_instruction_data_len(), 0);
     }
 
     #[test]
-    fn get_nonce_pub_from_ix_ok() {
-        let (_, nonce_pubkey, tx) = nonced_transfer_tx();
-        let nonce_ix = uses_durable_nonce(&tx).unwrap();
+    fn test_check_account_locks() {
+        let mut tx = create_sample_transaction();
+        let num_keys = tx.message.account_keys.len();
+        assert_eq!(tx.check_account_locks(num_keys), Ok(()));
         assert_eq!(
-            get_nonce_pubkey_from_instruction(nonce_ix, &tx),
-            Some(&nonce_pubkey),
+            tx.check_account_locks(num_keys - 1),
+            Err(TransactionError::TooManyAccountLocks)
         );
-    }
-
-    #[test]
-    fn get_nonce_pub_from_ix_no_accounts_fail() {
-        let (_, _, tx) = nonced_transfer_tx();
-        let nonce_ix = uses_durable_nonce(&tx).unwrap();
-        let mut nonce_ix = nonce_ix.clone();
-        nonce_ix.accounts.clear();
-        assert_eq!(get_nonce_pubkey_from_instruction(&nonce_ix, &tx), None,);
-    }
-
-    #[test]
-    fn get_nonce_pub_from_ix_bad_acc_idx_fail() {
-        let (_, _, tx) = nonced_transfer_tx();
-        let nonce_ix = uses_durable_nonce(&tx).unwrap();
-        let mut nonce_ix = nonce_ix.clone();
-        nonce_ix.accounts[0] = 255u8;
-        assert_eq!(get_nonce_pubkey_from_instruction(&nonce_ix, &tx), None,);
-    }
 
-    #[test]
-    fn tx_keypair_pubkey_mismatch() {
-        let from_keypair = Keypair::new();
-        let from_pubkey = from_keypair.pubkey();
-        let to_pubkey = Pubkey::new_unique();
-        let instructions = [system_instruction::transfer(&from_pubkey, &to_pubkey, 42)];
-        let mut tx = Transaction::new_with_payer(&instructions, Some(&from_pubkey));
-        let unused_keypair = Keypair::new();
-        let err = tx
-            .try_partial_sign(&[&from_keypair, &unused_keypair], Hash::default())
-            .unwrap_err();
-        assert_eq!(err, SignerError::KeypairPubkeyMismatch);
+        // Duplicate keys only take one lock
+        tx.message.account_keys.push(tx.message.account_keys[1]);
+        assert_eq!(tx.check_account_locks(num_keys), Ok(()));
     }
 }
@@ -8,13 +8,14 @@ use {
         precompiles::verify_if_precompile,
         program_utils::limited_deserialize,
         pubkey::Pubkey,
-        sanitize::Sanitize,
+        sanitize::{Sanitize, SanitizeError},
         signature::Signature,
         solana_sdk::feature_set,
         transaction::{Result, Transaction, TransactionError, VersionedTransaction},
     },
     solana_program::{system_instruction::SystemInstruction, system_program},
     std::sync::Arc,
+    thiserror::Error,
 };
 
 /// Sanitized transaction and the hash of its message
@@ -26,6 +27,27 @@ pub struct SanitizedTransaction {
     signatures: Vec<Signature>,
 }
 
+/// Reasons `SanitizedTransaction::try_from_transaction_for_tests` might reject
+/// a transaction
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum SanitizeTransactionError {
+    /// An account key appears more than once
+    #[error("duplicate account key")]
+    DuplicateAccountKey,
+    /// An instruction's program id index is out of bounds or refers to the fee payer
+    #[error("invalid program index")]
+    InvalidProgramIndex,
+    /// A header count or account index is out of bounds
+    #[error("index out of bounds")]
+    IndexOutOfBounds,
+    /// A value is out of bounds
+    #[error("value out of bounds")]
+    ValueOutOfBounds,
+    /// A value is invalid
+    #[error("invalid value")]
+    InvalidValue,
+}
+
 /// Set of accounts that must be locked for safe transaction processing
 #[derive(Debug, Clone, Default)]
 pub struct TransactionAccountLocks<'a> {
@@ -89,6 +111,37 @@ impl SanitizedTransaction {
         }
     }
 
+    /// Create a sanitized transaction from a legacy transaction, reporting why
+    /// sanitization failed in more detail than `TransactionError`. Used for
+    /// tests only.
+    pub fn try_from_transaction_for_tests(
+        tx: Transaction,
+    ) -> std::result::Result<Self, SanitizeTransactionError> {
+        let num_account_keys = tx.message.account_keys.len();
+        if tx.message.instructions.iter().any(|instruction| {
+            // The fee payer can't be invoked as a program
+            instruction.program_id_index == 0
+                || instruction.program_id_index as usize >= num_account_keys
+        }) {
+            return Err(SanitizeTransactionError::InvalidProgramIndex);
+        }
+        tx.sanitize().map_err(|err| match err {
+            SanitizeError::IndexOutOfBounds => SanitizeTransactionError::IndexOutOfBounds,
+            SanitizeError::ValueOutOfBounds => SanitizeTransactionError::ValueOutOfBounds,
+            SanitizeError::InvalidValue => SanitizeTransactionError::InvalidValue,
+        })?;
+        if tx.message.has_duplicates() {
+            return Err(SanitizeTransactionError::DuplicateAccountKey);
+        }
+
+        Ok(Self {
+            message_hash: tx.message.hash(),
+            message: SanitizedMessage::Legacy(tx.message),
+            is_simple_vote_tx: false,
+            signatures: tx.signatures,
+        })
+    }
+
     /// Return the first signature for this transaction.
     ///
     /// Notes:
@@ -115,6 +168,14 @@ impl SanitizedTransaction {
         &self.message_hash
     }
 
+    /// Return the fee payer of this transaction
+    ///
+    /// Sanitized transactions always have a fee payer since their messages
+    /// require at least one signature.
+    pub fn fee_payer(&self) -> &Pubkey {
+        self.message.fee_payer()
+    }
+
     /// Returns true if this transaction is a simple vote
     pub fn is_simple_vote_transaction(&self) -> bool {
         self.is_simple_vote_tx
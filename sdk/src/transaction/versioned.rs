@@ -6,6 +6,7 @@ use {
     crate::{
         hash::Hash,
         message::VersionedMessage,
+        pubkey::Pubkey,
         sanitize::{Sanitize, SanitizeError},
         short_vec,
         signature::Signature,
@@ -66,6 +67,15 @@ impl VersionedTransaction {
         }
     }
 
+    /// Return the fee payer, which is the first account key, or `None` if the
+    /// message doesn't require any signatures and so has no fee payer
+    pub fn fee_payer(&self) -> Option<&Pubkey> {
+        if self.message.header().num_required_signatures == 0 {
+            return None;
+        }
+        self.message.unmapped_keys_iter().next()
+    }
+
     /// Verify the transaction and hash its message
     pub fn verify_and_hash_message(&self) -> Result<Hash> {
         let message_bytes = self.message.serialize();
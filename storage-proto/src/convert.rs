@@ -554,6 +554,17 @@ impl TryFrom<tx_by_addr::TransactionError> for TransactionError {
             18 => TransactionError::UnsupportedVersion,
             19 => TransactionError::InvalidWritableAccount,
             20 => TransactionError::WouldExceedMaxAccountCostLimit,
+            21 => TransactionError::InvalidNonce,
+            22 => {
+                let signature_count_mismatch = transaction_error
+                    .signature_count_mismatch
+                    .ok_or("Invalid SignatureCountMismatch")?;
+                TransactionError::SignatureCountMismatch {
+                    expected: signature_count_mismatch.expected as u8,
+                    actual: signature_count_mismatch.actual as usize,
+                }
+            }
+            23 => TransactionError::TooManyAccountLocks,
             _ => return Err("Invalid TransactionError"),
         })
     }
@@ -624,6 +635,13 @@ impl From<TransactionError> for tx_by_addr::TransactionError {
                 TransactionError::WouldExceedMaxAccountCostLimit => {
                     tx_by_addr::TransactionErrorType::WouldExceedMaxAccountCostLimit
                 }
+                TransactionError::InvalidNonce => tx_by_addr::TransactionErrorType::InvalidNonce,
+                TransactionError::SignatureCountMismatch { .. } => {
+                    tx_by_addr::TransactionErrorType::SignatureCountMismatch
+                }
+                TransactionError::TooManyAccountLocks => {
+                    tx_by_addr::TransactionErrorType::TooManyAccountLocks
+                }
             } as i32,
             instruction_error: match transaction_error {
                 TransactionError::InstructionError(index, ref instruction_error) => {
@@ -789,6 +807,15 @@ impl From<TransactionError> for tx_by_addr::TransactionError {
                 }
                 _ => None,
             },
+            signature_count_mismatch: match transaction_error {
+                TransactionError::SignatureCountMismatch { expected, actual } => {
+                    Some(tx_by_addr::SignatureCountMismatch {
+                        expected: expected as u32,
+                        actual: actual as u64,
+                    })
+                }
+                _ => None,
+            },
         }
     }
 }
@@ -1043,6 +1070,33 @@ mod test {
             tx_by_addr_transaction_error.try_into().unwrap()
         );
 
+        let transaction_error = TransactionError::InvalidNonce;
+        let tx_by_addr_transaction_error: tx_by_addr::TransactionError =
+            transaction_error.clone().into();
+        assert_eq!(
+            transaction_error,
+            tx_by_addr_transaction_error.try_into().unwrap()
+        );
+
+        let transaction_error = TransactionError::SignatureCountMismatch {
+            expected: 2,
+            actual: 1,
+        };
+        let tx_by_addr_transaction_error: tx_by_addr::TransactionError =
+            transaction_error.clone().into();
+        assert_eq!(
+            transaction_error,
+            tx_by_addr_transaction_error.try_into().unwrap()
+        );
+
+        let transaction_error = TransactionError::TooManyAccountLocks;
+        let tx_by_addr_transaction_error: tx_by_addr::TransactionError =
+            transaction_error.clone().into();
+        assert_eq!(
+            transaction_error,
+            tx_by_addr_transaction_error.try_into().unwrap()
+        );
+
         let transaction_error = TransactionError::UnsupportedVersion;
         let tx_by_addr_transaction_error: tx_by_addr::TransactionError =
             transaction_error.clone().into();